@@ -0,0 +1,119 @@
+//! A common interface over the quantile backends so callers can swap an exact
+//! brute state for a t-digest or a streaming summary without touching the
+//! reporting code.
+
+use crate::stats::Statistics;
+
+/// Behaviour shared by every quantile backend.
+///
+/// `merge` is lossless only for the mergeable backends (`Brute`, `TDigest`,
+/// `Zw`), which override it. The default is a no-op so the non-mergeable
+/// sketches still satisfy the trait; those backends (`Ckms`, `Reservoir`)
+/// override it to panic on a non-empty `other` rather than silently discard its
+/// samples. Do not route per-shard reservoir/ckms states through `merge`.
+pub trait QuantileEstimator {
+    fn insert(&mut self, x: f64);
+    fn quantile(&self, q: f64) -> f64;
+    fn count(&self) -> usize;
+
+    fn merge(&mut self, _other: &Self)
+    where
+        Self: Sized,
+    {
+    }
+}
+
+/// Build a [`Statistics`] from any estimator, filling every percentile field
+/// through the trait so the exact and approximate paths share one layout.
+///
+/// The estimators do not retain the running mean, so `avg` is left as `NaN`;
+/// `max` is taken as the `q = 1.0` quantile.
+pub fn statistics_from_estimator<E: QuantileEstimator>(e: &E) -> Statistics {
+    let cnt = e.count();
+    if cnt == 0 {
+        return Statistics {
+            avg: f64::NAN,
+            p10: f64::NAN,
+            p30: f64::NAN,
+            p50: f64::NAN,
+            p80: f64::NAN,
+            p90: f64::NAN,
+            p95: f64::NAN,
+            p99: f64::NAN,
+            p999: f64::NAN,
+            max: f64::NAN,
+            cnt: 0,
+        };
+    }
+
+    Statistics {
+        avg: f64::NAN,
+        p10: e.quantile(0.1),
+        p30: e.quantile(0.3),
+        p50: e.quantile(0.5),
+        p80: e.quantile(0.8),
+        p90: e.quantile(0.9),
+        p95: e.quantile(0.95),
+        p99: e.quantile(0.99),
+        p999: e.quantile(0.999),
+        max: e.quantile(1.0),
+        cnt,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quantile_brute::BruteQuantileState;
+    use crate::quantile_ckms::CkmsQuantileState;
+    use crate::quantile_reservoir::ReservoirQuantileState;
+    use crate::quantile_tdigest::TDigestQuantileState;
+    use crate::quantile_zw::ZwQuantileState;
+
+    fn feed<E: QuantileEstimator>(e: &mut E, data: &[f64]) {
+        for &x in data {
+            e.insert(x);
+        }
+    }
+
+    /// The same workload through each backend should yield a `Statistics` with
+    /// the same layout and p99 values that agree to within a few percent of the
+    /// exact answer.
+    #[test]
+    fn backends_agree_through_statistics() {
+        let data: Vec<f64> = (1..=10_000).map(|i| i as f64).collect();
+
+        let mut brute = BruteQuantileState::new();
+        feed(&mut brute, &data);
+        let exact = statistics_from_estimator(&brute);
+        assert_eq!(exact.cnt, 10_000);
+        assert!((exact.p99 - 9_900.0).abs() < 2.0, "exact p99 {}", exact.p99);
+
+        let tolerance = exact.p99 * 0.05;
+
+        let mut td = TDigestQuantileState::new(data.len());
+        feed(&mut td, &data);
+        let s = statistics_from_estimator(&td);
+        assert_eq!(s.cnt, exact.cnt);
+        assert!((s.p99 - exact.p99).abs() < tolerance, "t-digest p99 {}", s.p99);
+
+        let mut ckms = CkmsQuantileState::new(0.001);
+        feed(&mut ckms, &data);
+        let s = statistics_from_estimator(&ckms);
+        assert_eq!(s.cnt, exact.cnt);
+        assert!((s.p99 - exact.p99).abs() < tolerance, "ckms p99 {}", s.p99);
+
+        let mut zw = ZwQuantileState::new(0.01);
+        feed(&mut zw, &data);
+        let s = statistics_from_estimator(&zw);
+        assert_eq!(s.cnt, exact.cnt);
+        assert!((s.p99 - exact.p99).abs() < tolerance, "zw p99 {}", s.p99);
+
+        let mut reservoir = ReservoirQuantileState::new_seeded(2_000, 7);
+        feed(&mut reservoir, &data);
+        let s = statistics_from_estimator(&reservoir);
+        assert_eq!(s.cnt, exact.cnt);
+        // A reservoir only retains a sample, so allow a wider tolerance.
+        assert!((s.p99 - exact.p99).abs() < exact.p99 * 0.1, "reservoir p99 {}", s.p99);
+    }
+}
@@ -0,0 +1,253 @@
+//! A common interface over the quantile backends so callers can swap an exact
+//! brute state for a t-digest or a streaming summary without touching the
+//! reporting code.
+
+use std::time::Duration;
+
+use crate::stats::{Statistics, TimeUnit};
+
+/// Behaviour shared by every quantile backend.
+///
+/// `merge` is lossless only for the mergeable backends (`Brute`, `TDigest`,
+/// `Zw`), which override it. The default is a no-op so the non-mergeable
+/// sketches still satisfy the trait; those backends (`Ckms`, `Reservoir`)
+/// override it to panic on a non-empty `other` rather than silently discard its
+/// samples. Do not route per-shard reservoir/ckms states through `merge`.
+pub trait QuantileEstimator {
+    fn insert(&mut self, x: f64);
+    fn quantile(&self, q: f64) -> f64;
+    fn count(&self) -> usize;
+
+    fn merge(&mut self, _other: &Self)
+    where
+        Self: Sized,
+    {
+    }
+
+    /// Alias for [`count`](Self::count). Callers that treat every backend as
+    /// approximate tend to reach for this name; exact backends answer it
+    /// identically to `count`.
+    fn estimate_count(&self) -> usize {
+        self.count()
+    }
+
+    /// Insert a [`Duration`], scaled to `unit`, without the caller having to
+    /// convert to `f64` by hand first (and risk a millis/micros mixup).
+    fn insert_duration(&mut self, d: Duration, unit: TimeUnit) {
+        self.insert(unit.scale(d));
+    }
+}
+
+/// Build a [`Statistics`] from any estimator, filling every percentile field
+/// through the trait so the exact and approximate paths share one layout.
+///
+/// The estimators do not retain the running mean, so `avg` (and `sum`, for
+/// the same reason) are left as `NaN`; `min`/`max` are taken as the
+/// `q = 0.0`/`q = 1.0` quantiles.
+pub fn statistics_from_estimator<E: QuantileEstimator>(e: &E) -> Statistics {
+    let cnt = e.count();
+    if cnt == 0 {
+        return Statistics {
+            avg: f64::NAN,
+            sum: f64::NAN,
+            p1: f64::NAN,
+            p5: f64::NAN,
+            p10: f64::NAN,
+            p25: f64::NAN,
+            p30: f64::NAN,
+            p50: f64::NAN,
+            p75: f64::NAN,
+            p80: f64::NAN,
+            p90: f64::NAN,
+            p95: f64::NAN,
+            p99: f64::NAN,
+            p999: f64::NAN,
+            p9999: f64::NAN,
+            min: f64::NAN,
+            max: f64::NAN,
+            stddev: f64::NAN,
+            variance: f64::NAN,
+            cnt: 0,
+        };
+    }
+
+    Statistics {
+        avg: f64::NAN,
+        sum: f64::NAN,
+        stddev: f64::NAN,
+        variance: f64::NAN,
+        p1: e.quantile(0.01),
+        p5: e.quantile(0.05),
+        p10: e.quantile(0.1),
+        p25: e.quantile(0.25),
+        p30: e.quantile(0.3),
+        p50: e.quantile(0.5),
+        p75: e.quantile(0.75),
+        p80: e.quantile(0.8),
+        p90: e.quantile(0.9),
+        p95: e.quantile(0.95),
+        p99: e.quantile(0.99),
+        p999: e.quantile(0.999),
+        p9999: e.quantile(0.9999),
+        min: e.quantile(0.0),
+        max: e.quantile(1.0),
+        cnt,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quantile_brute::BruteQuantileState;
+    use crate::quantile_ckms::CkmsQuantileState;
+    use crate::quantile_reservoir::ReservoirQuantileState;
+    use crate::quantile_tdigest::TDigestQuantileState;
+    use crate::quantile_zw::ZwQuantileState;
+
+    fn feed<E: QuantileEstimator>(e: &mut E, data: &[f64]) {
+        for &x in data {
+            e.insert(x);
+        }
+    }
+
+    /// An estimator never retains the raw sum, so `sum` reports `NaN` just
+    /// like `avg`, whether or not any samples were inserted.
+    #[test]
+    fn statistics_from_estimator_sum_is_always_nan() {
+        let mut brute = BruteQuantileState::new();
+        assert!(statistics_from_estimator(&brute).sum.is_nan());
+        feed(&mut brute, &[1.0, 2.0, 3.0]);
+        assert!(statistics_from_estimator(&brute).sum.is_nan());
+    }
+
+    /// The same workload through each backend should yield a `Statistics` with
+    /// the same layout and p99 values that agree to within a few percent of the
+    /// exact answer.
+    #[test]
+    fn backends_agree_through_statistics() {
+        let data: Vec<f64> = (1..=10_000).map(|i| i as f64).collect();
+
+        let mut brute = BruteQuantileState::new();
+        feed(&mut brute, &data);
+        let exact = statistics_from_estimator(&brute);
+        assert_eq!(exact.cnt, 10_000);
+        assert!((exact.p99 - 9_900.0).abs() < 2.0, "exact p99 {}", exact.p99);
+
+        let tolerance = exact.p99 * 0.05;
+
+        let mut td = TDigestQuantileState::new(data.len());
+        feed(&mut td, &data);
+        let s = statistics_from_estimator(&td);
+        assert_eq!(s.cnt, exact.cnt);
+        assert!((s.p99 - exact.p99).abs() < tolerance, "t-digest p99 {}", s.p99);
+
+        let mut ckms = CkmsQuantileState::new(0.001);
+        feed(&mut ckms, &data);
+        let s = statistics_from_estimator(&ckms);
+        assert_eq!(s.cnt, exact.cnt);
+        assert!((s.p99 - exact.p99).abs() < tolerance, "ckms p99 {}", s.p99);
+
+        let mut zw = ZwQuantileState::new(0.01);
+        feed(&mut zw, &data);
+        let s = statistics_from_estimator(&zw);
+        assert_eq!(s.cnt, exact.cnt);
+        assert!((s.p99 - exact.p99).abs() < tolerance, "zw p99 {}", s.p99);
+
+        let mut reservoir = ReservoirQuantileState::new_seeded(2_000, 7);
+        feed(&mut reservoir, &data);
+        let s = statistics_from_estimator(&reservoir);
+        assert_eq!(s.cnt, exact.cnt);
+        // A reservoir only retains a sample, so allow a wider tolerance.
+        assert!((s.p99 - exact.p99).abs() < exact.p99 * 0.1, "reservoir p99 {}", s.p99);
+    }
+
+    /// `QuantileEstimator` must stay object-safe — `merge`'s `where Self:
+    /// Sized` bound keeps it out of the vtable, and every other method
+    /// dispatches through `&self`/`&mut self` with no generic parameters or
+    /// `Self`-returning signature — so a config-selected mix of backends can
+    /// live behind one `Vec<Box<dyn QuantileEstimator>>` rather than an enum
+    /// with a match arm per backend.
+    #[test]
+    fn boxed_trait_objects_of_every_backend_agree_on_p50() {
+        let data: Vec<f64> = (1..=10_000).map(|i| i as f64).collect();
+
+        let mut brute = BruteQuantileState::new();
+        feed(&mut brute, &data);
+        let mut td = TDigestQuantileState::new(data.len());
+        feed(&mut td, &data);
+        let mut ckms = CkmsQuantileState::new(0.001);
+        feed(&mut ckms, &data);
+        let mut zw = ZwQuantileState::new(0.01);
+        feed(&mut zw, &data);
+        let mut reservoir = ReservoirQuantileState::new_seeded(2_000, 7);
+        feed(&mut reservoir, &data);
+
+        let estimators: Vec<Box<dyn QuantileEstimator>> =
+            vec![Box::new(brute), Box::new(td), Box::new(ckms), Box::new(zw), Box::new(reservoir)];
+
+        let exact_p50 = 5_000.0;
+        let tolerance = exact_p50 * 0.1;
+        for estimator in &estimators {
+            assert_eq!(estimator.count(), data.len());
+            assert!(
+                (estimator.quantile(0.5) - exact_p50).abs() < tolerance,
+                "p50 {} outside tolerance of {}",
+                estimator.quantile(0.5),
+                exact_p50
+            );
+        }
+    }
+
+    /// A one-element dataset must return that element for every `q` on every
+    /// backend, not just the exact ones. `TDigestQuantileState` used to
+    /// answer `NaN` here: a single insert sits in its buffer until
+    /// `buffer_capacity` samples accumulate, and `quantile` only used to look
+    /// at the already-flushed digest, ignoring the buffer that `rank` and
+    /// `centroids` already accounted for.
+    #[test]
+    fn single_sample_quantile_is_that_sample_on_every_backend() {
+        let qs = [0.0, 0.01, 0.25, 0.5, 0.75, 0.99, 1.0];
+
+        let mut brute = BruteQuantileState::new();
+        brute.insert(42.0);
+        for &q in &qs {
+            assert_eq!(QuantileEstimator::quantile(&brute, q), 42.0);
+        }
+
+        let mut td = TDigestQuantileState::new(1);
+        td.insert(42.0);
+        for &q in &qs {
+            assert_eq!(td.quantile(q), 42.0, "t-digest quantile({q}) on a single buffered sample");
+        }
+
+        let mut ckms = CkmsQuantileState::new(0.01);
+        ckms.insert(42.0);
+        for &q in &qs {
+            assert_eq!(QuantileEstimator::quantile(&ckms, q), 42.0);
+        }
+
+        let mut zw = ZwQuantileState::new(0.01);
+        zw.insert(42.0);
+        for &q in &qs {
+            assert_eq!(QuantileEstimator::quantile(&zw, q), 42.0);
+        }
+
+        let mut reservoir = ReservoirQuantileState::new_seeded(10, 7);
+        reservoir.insert(42.0);
+        for &q in &qs {
+            assert_eq!(QuantileEstimator::quantile(&reservoir, q), 42.0);
+        }
+    }
+
+    #[test]
+    fn insert_duration_scales_before_inserting() {
+        use std::time::Duration;
+
+        let mut brute = BruteQuantileState::new();
+        brute.insert_duration(Duration::from_millis(100), TimeUnit::Millis);
+        brute.insert_duration(Duration::from_secs(1), TimeUnit::Millis);
+        assert_eq!(QuantileEstimator::count(&brute), 2);
+        assert_eq!(QuantileEstimator::quantile(&brute, 0.0), 100.0);
+        assert_eq!(QuantileEstimator::quantile(&brute, 1.0), 1_000.0);
+    }
+}
@@ -0,0 +1,148 @@
+//! Lock-free logarithmic-bucketing histogram for the hot path of a massive
+//! test.
+//!
+//! Unlike the `&mut self` quantile states, a [`Histogram`] is shared by every
+//! worker thread: `measure` takes `&self` and does a single `Relaxed` atomic
+//! increment with no allocation. Percentiles are reconstructed only at report
+//! time via [`Histogram::statistics`], with bounded (<0.5%) relative error.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::stats::Statistics;
+
+/// Number of preallocated buckets (`2^16`).
+const BUCKET_COUNT: usize = 1 << 16;
+
+/// Buckets per natural-log unit. A spacing of `exp(1/SCALE) - 1 ≈ 1/SCALE`
+/// keeps each bucket's half-width under 0.5% relative error.
+const SCALE: f64 = 128.0;
+
+/// A fixed-size histogram with logarithmically spaced buckets.
+#[derive(Debug)]
+pub struct Histogram {
+    buckets: Box<[AtomicU64]>,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Histogram {
+    pub fn new() -> Self {
+        let mut buckets = Vec::with_capacity(BUCKET_COUNT);
+        buckets.resize_with(BUCKET_COUNT, || AtomicU64::new(0));
+        Self {
+            buckets: buckets.into_boxed_slice(),
+        }
+    }
+
+    /// Map a value to its bucket index via the compressed logarithmic
+    /// transform, clamped into range. Non-positive values fall in bucket 0.
+    fn bucket_of(x: f64) -> usize {
+        if !(x > 1.0) {
+            return 0;
+        }
+        let idx = (x.ln() * SCALE).round();
+        (idx as usize).min(BUCKET_COUNT - 1)
+    }
+
+    /// The representative value of a bucket (the reverse of [`Self::bucket_of`]).
+    fn value_of(bucket: usize) -> f64 {
+        if bucket == 0 {
+            return 1.0;
+        }
+        (bucket as f64 / SCALE).exp()
+    }
+
+    /// Record a sample. Safe to call concurrently from many threads.
+    pub fn measure(&self, x: f64) {
+        let bucket = Self::bucket_of(x);
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Reconstruct percentiles from the bucket counts.
+    pub fn statistics(&self) -> Statistics {
+        let counts: Vec<u64> = self
+            .buckets
+            .iter()
+            .map(|c| c.load(Ordering::Relaxed))
+            .collect();
+        let total: u64 = counts.iter().sum();
+        if total == 0 {
+            return Statistics {
+                avg: f64::NAN,
+                p10: f64::NAN,
+                p30: f64::NAN,
+                p50: f64::NAN,
+                p80: f64::NAN,
+                p90: f64::NAN,
+                p95: f64::NAN,
+                p99: f64::NAN,
+                p999: f64::NAN,
+                max: f64::NAN,
+                cnt: 0,
+            };
+        }
+
+        let mut weighted_sum = 0.0;
+        let mut max = f64::NAN;
+        for (bucket, &count) in counts.iter().enumerate() {
+            if count == 0 {
+                continue;
+            }
+            let value = Self::value_of(bucket);
+            weighted_sum += value * count as f64;
+            max = value;
+        }
+        let avg = (weighted_sum / total as f64 * 100.0).round() / 100.0;
+
+        // Walk the buckets once, reading off each percentile as its rank is
+        // crossed. `targets` must stay sorted by quantile.
+        let targets = [
+            (0.1, 0usize),
+            (0.3, 1),
+            (0.5, 2),
+            (0.8, 3),
+            (0.9, 4),
+            (0.95, 5),
+            (0.99, 6),
+            (0.999, 7),
+        ];
+        let mut out = [f64::NAN; 8];
+        let mut cumulative = 0u64;
+        let mut next = 0;
+        for (bucket, &count) in counts.iter().enumerate() {
+            if count == 0 {
+                continue;
+            }
+            cumulative += count;
+            let value = Self::value_of(bucket);
+            while next < targets.len() {
+                let (q, slot) = targets[next];
+                let rank = (q * total as f64).ceil() as u64;
+                if cumulative >= rank.max(1) {
+                    out[slot] = value;
+                    next += 1;
+                } else {
+                    break;
+                }
+            }
+        }
+
+        Statistics {
+            avg,
+            p10: out[0],
+            p30: out[1],
+            p50: out[2],
+            p80: out[3],
+            p90: out[4],
+            p95: out[5],
+            p99: out[6],
+            p999: out[7],
+            max,
+            cnt: total as usize,
+        }
+    }
+}
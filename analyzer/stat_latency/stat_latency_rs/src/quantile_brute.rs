@@ -1,6 +1,9 @@
 use std::cmp::Ordering;
 
-fn exact_quantile(values: &[f64], q: f64) -> f64 {
+use crate::estimator::QuantileEstimator;
+use crate::stats::QuantileInterpolation;
+
+pub(crate) fn exact_quantile(values: &[f64], q: f64, interp: QuantileInterpolation) -> f64 {
     if values.is_empty() {
         return f64::NAN;
     }
@@ -20,7 +23,7 @@ fn exact_quantile(values: &[f64], q: f64) -> f64 {
     }
 
     let w = h - (lo as f64);
-    sorted[lo] + (sorted[hi] - sorted[lo]) * w
+    interp.apply(sorted[lo], sorted[hi], w)
 }
 
 #[derive(Debug)]
@@ -37,7 +40,44 @@ impl BruteQuantileState {
         self.values.push(x);
     }
 
-    pub fn quantile(&self, q: f64) -> f64 {
-        exact_quantile(&self.values, q)
+    pub fn quantile(&self, q: f64, interp: QuantileInterpolation) -> f64 {
+        exact_quantile(&self.values, q, interp)
+    }
+}
+
+impl QuantileEstimator for BruteQuantileState {
+    fn insert(&mut self, x: f64) {
+        self.values.push(x);
+    }
+
+    fn quantile(&self, q: f64) -> f64 {
+        exact_quantile(&self.values, q, QuantileInterpolation::Linear)
+    }
+
+    fn count(&self) -> usize {
+        self.values.len()
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.values.extend_from_slice(&other.values);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recovers_exact_quantiles() {
+        let mut state = BruteQuantileState::new();
+        for i in 1..=100 {
+            state.insert(i as f64);
+        }
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+        assert!(close(state.quantile(0.5, QuantileInterpolation::Linear), 50.5));
+        assert!(close(state.quantile(0.5, QuantileInterpolation::Lower), 50.0));
+        assert!(close(state.quantile(0.5, QuantileInterpolation::Higher), 51.0));
+        assert!(close(state.quantile(0.5, QuantileInterpolation::Nearest), 50.0));
+        assert!(close(state.quantile(0.5, QuantileInterpolation::Midpoint), 50.5));
     }
 }
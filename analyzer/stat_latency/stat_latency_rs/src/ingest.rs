@@ -0,0 +1,210 @@
+//! Streaming ingest from newline-delimited JSON, for collectors that emit
+//! files too large to load into memory in one shot. Reads and inserts one
+//! line at a time so the caller's `BufRead` (a file, a pipe, a
+//! `Cursor<&[u8]>` in tests) sets the only memory bound.
+
+use std::fmt;
+use std::io::{self, BufRead};
+
+use crate::estimator::QuantileEstimator;
+use crate::stats::{f64_from_stat_path, statistics_from_vec, Statistics};
+
+/// Failure reading the underlying stream. Malformed or missing-field lines
+/// are not an `IngestError` — they're counted and skipped, since one bad
+/// line in a multi-gigabyte collector dump shouldn't abort the whole run;
+/// this only covers the reader itself failing.
+#[derive(Debug)]
+pub struct IngestError(io::Error);
+
+impl fmt::Display for IngestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to read ingest stream: {}", self.0)
+    }
+}
+
+impl std::error::Error for IngestError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+impl From<io::Error> for IngestError {
+    fn from(err: io::Error) -> Self {
+        IngestError(err)
+    }
+}
+
+/// Read newline-delimited JSON objects from `reader`, pull `field` (a
+/// [`f64_from_stat_path`] dot-path, e.g. `"latency"` or `"latency.p99"`) out
+/// of each, and [`insert`](QuantileEstimator::insert) the finite ones into
+/// `state`. Returns how many lines were accepted. A line that fails to parse
+/// as a JSON object, is missing `field`, or has a non-finite value there is
+/// silently skipped rather than aborting the run; only an I/O error reading
+/// `reader` itself is propagated.
+pub fn ingest_jsonl<R: BufRead>(
+    reader: R,
+    field: &str,
+    state: &mut impl QuantileEstimator,
+) -> Result<usize, IngestError> {
+    let mut accepted = 0;
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let map: std::collections::HashMap<String, serde_json::Value> =
+            match serde_json::from_str(&line) {
+                Ok(map) => map,
+                Err(_) => continue,
+            };
+        let Some(value) = f64_from_stat_path(&map, field) else {
+            continue;
+        };
+        if value.is_finite() {
+            state.insert(value);
+            accepted += 1;
+        }
+    }
+    Ok(accepted)
+}
+
+/// Failure reading or parsing a CSV stream in [`statistics_from_csv`].
+#[derive(Debug)]
+pub enum CsvError {
+    /// The underlying reader failed.
+    Io(io::Error),
+    /// The stream had no header row to read `column` from.
+    Empty,
+    /// `column` did not appear in the header row.
+    ColumnNotFound(String),
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CsvError::Io(err) => write!(f, "failed to read CSV stream: {err}"),
+            CsvError::Empty => write!(f, "CSV stream has no header row"),
+            CsvError::ColumnNotFound(column) => write!(f, "column {column:?} not found in CSV header"),
+        }
+    }
+}
+
+impl std::error::Error for CsvError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CsvError::Io(err) => Some(err),
+            CsvError::Empty | CsvError::ColumnNotFound(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for CsvError {
+    fn from(err: io::Error) -> Self {
+        CsvError::Io(err)
+    }
+}
+
+/// Compute [`Statistics`] over one column of a CSV stream with a header row.
+/// Rows are split on `,` with no quoting support — a simple comma-split, not
+/// a full CSV parser, is enough for the plain unquoted-numeric exports this
+/// reads today. Cells that are empty or don't parse as a finite `f64` are
+/// skipped rather than aborting the run, the same tolerance
+/// [`ingest_jsonl`] gives malformed lines. A short row missing the target
+/// column entirely is treated the same way.
+pub fn statistics_from_csv<R: BufRead>(reader: R, column: &str) -> Result<Statistics, CsvError> {
+    let mut lines = reader.lines();
+    let header = lines.next().ok_or(CsvError::Empty)??;
+    let index = header
+        .split(',')
+        .position(|name| name.trim() == column)
+        .ok_or_else(|| CsvError::ColumnNotFound(column.to_string()))?;
+
+    let mut values = Vec::new();
+    for line in lines {
+        let line = line?;
+        let Some(cell) = line.split(',').nth(index) else {
+            continue;
+        };
+        if let Ok(value) = cell.trim().parse::<f64>() {
+            if value.is_finite() {
+                values.push(value);
+            }
+        }
+    }
+    Ok(statistics_from_vec(values))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quantile_brute::BruteQuantileState;
+    use std::io::Cursor;
+
+    #[test]
+    fn ingest_jsonl_extracts_field_and_counts_accepted() {
+        let data = "{\"latency\":1.0}\n{\"latency\":2.0}\n{\"latency\":3.0}\n";
+        let mut state = BruteQuantileState::new();
+        let accepted = ingest_jsonl(Cursor::new(data), "latency", &mut state).unwrap();
+        assert_eq!(accepted, 3);
+        assert_eq!(state.count(), 3);
+    }
+
+    #[test]
+    fn ingest_jsonl_skips_malformed_and_missing_field_lines() {
+        let data = "not json\n{\"other\":1.0}\n{\"latency\":5.0}\n\n{\"latency\":\"nope\"}\n";
+        let mut state = BruteQuantileState::new();
+        let accepted = ingest_jsonl(Cursor::new(data), "latency", &mut state).unwrap();
+        assert_eq!(accepted, 1);
+        assert_eq!(state.count(), 1);
+    }
+
+    #[test]
+    fn ingest_jsonl_supports_dotted_paths() {
+        let data = "{\"latency\":{\"p99\":42.0}}\n";
+        let mut state = BruteQuantileState::new();
+        let accepted = ingest_jsonl(Cursor::new(data), "latency.p99", &mut state).unwrap();
+        assert_eq!(accepted, 1);
+        assert_eq!(QuantileEstimator::quantile(&state, 0.0), 42.0);
+    }
+
+    #[test]
+    fn ingest_jsonl_rejects_non_finite_values() {
+        let data = "{\"latency\":NaN}\n{\"latency\":1.0}\n";
+        let mut state = BruteQuantileState::new();
+        // `NaN` is not valid JSON, so this line is skipped as malformed
+        // rather than reaching the finiteness check; kept here as
+        // documentation of that boundary.
+        let accepted = ingest_jsonl(Cursor::new(data), "latency", &mut state).unwrap();
+        assert_eq!(accepted, 1);
+    }
+
+    #[test]
+    fn statistics_from_csv_reads_the_named_column() {
+        let data = "name,latency_ms\nreq1,1.0\nreq2,2.0\nreq3,3.0\n";
+        let stats = statistics_from_csv(Cursor::new(data), "latency_ms").unwrap();
+        assert_eq!(stats.cnt, 3);
+        assert_eq!(stats.min, 1.0);
+        assert_eq!(stats.max, 3.0);
+    }
+
+    #[test]
+    fn statistics_from_csv_skips_unparseable_and_empty_cells() {
+        let data = "latency_ms\n1.0\n\nnot-a-number\n3.0\n";
+        let stats = statistics_from_csv(Cursor::new(data), "latency_ms").unwrap();
+        assert_eq!(stats.cnt, 2);
+    }
+
+    #[test]
+    fn statistics_from_csv_errors_on_missing_column() {
+        let data = "a,b\n1,2\n";
+        assert!(matches!(
+            statistics_from_csv(Cursor::new(data), "c"),
+            Err(CsvError::ColumnNotFound(col)) if col == "c"
+        ));
+    }
+
+    #[test]
+    fn statistics_from_csv_errors_on_empty_stream() {
+        assert!(matches!(statistics_from_csv(Cursor::new(""), "x"), Err(CsvError::Empty)));
+    }
+}
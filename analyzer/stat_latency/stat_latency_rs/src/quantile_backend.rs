@@ -0,0 +1,139 @@
+use std::fmt;
+
+use crate::estimator::QuantileEstimator;
+use crate::quantile_brute::BruteQuantileState;
+use crate::quantile_gk::GkQuantileState;
+use crate::quantile_tdigest::TDigestQuantileState;
+
+/// Which concrete backend a [`QuantileBackend`] wraps, e.g. selected by a
+/// `--quantile-backend brute|tdigest|gk` CLI flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackendKind {
+    Brute,
+    TDigest,
+    Gk,
+}
+
+/// A runtime-selectable quantile backend: one type a config struct can hold
+/// and dispatch on, for the common case of choosing among a fixed set of
+/// backends at startup rather than needing the flexibility (and the extra
+/// indirection) of a boxed `dyn QuantileEstimator`.
+#[derive(Debug)]
+pub enum QuantileBackend {
+    Brute(BruteQuantileState),
+    TDigest(TDigestQuantileState),
+    Gk(GkQuantileState),
+}
+
+impl QuantileBackend {
+    /// Construct the backend named by `kind`, using sensible defaults for any
+    /// backend that needs a tuning parameter (`TDigest` gets
+    /// `TDigestConfig::default()`, `Gk` gets `epsilon = 0.01`). Callers who
+    /// need a specific tuning should construct the inner state themselves and
+    /// wrap it directly, e.g. `QuantileBackend::TDigest(TDigestQuantileState::with_config(cfg))`.
+    pub fn new(kind: BackendKind) -> Self {
+        match kind {
+            BackendKind::Brute => QuantileBackend::Brute(BruteQuantileState::new()),
+            BackendKind::TDigest => QuantileBackend::TDigest(TDigestQuantileState::new(0)),
+            BackendKind::Gk => QuantileBackend::Gk(GkQuantileState::new(0.01)),
+        }
+    }
+
+    pub fn insert(&mut self, x: f64) {
+        match self {
+            QuantileBackend::Brute(s) => QuantileEstimator::insert(s, x),
+            QuantileBackend::TDigest(s) => QuantileEstimator::insert(s, x),
+            QuantileBackend::Gk(s) => QuantileEstimator::insert(s, x),
+        }
+    }
+
+    pub fn quantile(&self, q: f64) -> f64 {
+        match self {
+            QuantileBackend::Brute(s) => QuantileEstimator::quantile(s, q),
+            QuantileBackend::TDigest(s) => QuantileEstimator::quantile(s, q),
+            QuantileBackend::Gk(s) => QuantileEstimator::quantile(s, q),
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        match self {
+            QuantileBackend::Brute(s) => QuantileEstimator::count(s),
+            QuantileBackend::TDigest(s) => QuantileEstimator::count(s),
+            QuantileBackend::Gk(s) => QuantileEstimator::count(s),
+        }
+    }
+
+    /// Merge `other` into `self`, delegating to the inner backend's own
+    /// `merge` (which panics if that backend isn't mergeable and `other` is
+    /// non-empty, per [`QuantileEstimator::merge`]'s contract). Returns
+    /// [`BackendMismatch`] instead of panicking when `self` and `other` wrap
+    /// different [`BackendKind`]s, since that's a caller bug worth reporting
+    /// cleanly rather than crashing on.
+    pub fn merge(&mut self, other: &Self) -> Result<(), BackendMismatch> {
+        match (self, other) {
+            (QuantileBackend::Brute(a), QuantileBackend::Brute(b)) => {
+                QuantileEstimator::merge(a, b);
+                Ok(())
+            }
+            (QuantileBackend::TDigest(a), QuantileBackend::TDigest(b)) => {
+                QuantileEstimator::merge(a, b);
+                Ok(())
+            }
+            (QuantileBackend::Gk(a), QuantileBackend::Gk(b)) => {
+                QuantileEstimator::merge(a, b);
+                Ok(())
+            }
+            _ => Err(BackendMismatch),
+        }
+    }
+}
+
+/// Returned by [`QuantileBackend::merge`] when the two backends wrap
+/// different [`BackendKind`]s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackendMismatch;
+
+impl fmt::Display for BackendMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cannot merge QuantileBackend variants of different kinds")
+    }
+}
+
+impl std::error::Error for BackendMismatch {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_and_quantile_delegate_to_inner_backend() {
+        for kind in [BackendKind::Brute, BackendKind::TDigest, BackendKind::Gk] {
+            let mut backend = QuantileBackend::new(kind);
+            for i in 1..=1_000 {
+                backend.insert(i as f64);
+            }
+            assert_eq!(backend.count(), 1_000);
+            let p50 = backend.quantile(0.5);
+            assert!((p50 - 500.0).abs() < 60.0, "{kind:?} p50 was {p50}");
+        }
+    }
+
+    #[test]
+    fn merge_same_kind_combines_counts() {
+        let mut left = QuantileBackend::new(BackendKind::Brute);
+        let mut right = QuantileBackend::new(BackendKind::Brute);
+        for i in 1..=50 {
+            left.insert(i as f64);
+            right.insert((i + 50) as f64);
+        }
+        left.merge(&right).expect("same-kind merge should succeed");
+        assert_eq!(left.count(), 100);
+    }
+
+    #[test]
+    fn merge_different_kinds_reports_mismatch() {
+        let mut brute = QuantileBackend::new(BackendKind::Brute);
+        let tdigest = QuantileBackend::new(BackendKind::TDigest);
+        assert_eq!(brute.merge(&tdigest), Err(BackendMismatch));
+    }
+}
@@ -0,0 +1,238 @@
+//! Bounded-memory reservoir sampling for long-running tests where keeping
+//! every sample (as [`BruteQuantileState`](crate::quantile_brute)) is
+//! infeasible.
+//!
+//! The unweighted mode is Vitter's Algorithm R: a uniform sample of at most `k`
+//! values in fixed memory. The weighted mode is A-Res (Efraimidis–Spirakis),
+//! which keeps the `k` items with the largest keys `u^(1/w)` in a min-heap so
+//! heavier-weighted latency samples are retained preferentially. Either way
+//! `quantile` just runs the exact quantile over the retained sample.
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+
+use crate::estimator::QuantileEstimator;
+use crate::quantile_brute::exact_quantile;
+use crate::stats::QuantileInterpolation;
+
+/// Default non-zero seed for the internal PRNG (the golden-ratio constant),
+/// giving reproducible sampling across runs.
+const DEFAULT_SEED: u64 = 0x9E37_79B9_7F4A_7C15;
+
+#[derive(Debug)]
+pub struct ReservoirQuantileState {
+    capacity: usize,
+    reservoir: Vec<f64>,
+    weighted: Option<BinaryHeap<Reverse<KeyedSample>>>,
+    seen: usize,
+    rng: Rng,
+}
+
+impl ReservoirQuantileState {
+    /// An unweighted reservoir retaining at most `k` values, using the fixed
+    /// [`DEFAULT_SEED`] for single-state reproducibility.
+    pub fn new(k: usize) -> Self {
+        Self::new_seeded(k, DEFAULT_SEED)
+    }
+
+    /// An unweighted reservoir seeded explicitly. Per-shard states in the
+    /// massive-test harness MUST be seeded distinctly, otherwise every shard's
+    /// PRNG stream is identical and their replacement decisions are correlated,
+    /// so the merged sample is not an independent uniform sample.
+    pub fn new_seeded(k: usize, seed: u64) -> Self {
+        Self {
+            capacity: k,
+            reservoir: Vec::with_capacity(k),
+            weighted: None,
+            seen: 0,
+            rng: Rng::new(seed),
+        }
+    }
+
+    /// A weighted reservoir (A-Res) retaining at most `k` values, biased
+    /// towards heavier items, using the fixed [`DEFAULT_SEED`].
+    pub fn new_weighted(k: usize) -> Self {
+        Self::new_weighted_seeded(k, DEFAULT_SEED)
+    }
+
+    /// A weighted reservoir seeded explicitly; seed shards distinctly for the
+    /// same reason as [`new_seeded`](Self::new_seeded).
+    pub fn new_weighted_seeded(k: usize, seed: u64) -> Self {
+        Self {
+            capacity: k,
+            reservoir: Vec::new(),
+            weighted: Some(BinaryHeap::with_capacity(k + 1)),
+            seen: 0,
+            rng: Rng::new(seed),
+        }
+    }
+
+    pub fn insert(&mut self, x: f64) {
+        if self.weighted.is_some() {
+            self.insert_weighted(x, 1.0);
+            return;
+        }
+        if self.reservoir.len() < self.capacity {
+            self.reservoir.push(x);
+        } else {
+            // Algorithm R: the (seen+1)-th item replaces a uniformly chosen
+            // slot with probability k/(seen+1).
+            let j = self.rng.below(self.seen + 1);
+            if j < self.capacity {
+                self.reservoir[j] = x;
+            }
+        }
+        self.seen += 1;
+    }
+
+    /// Insert a weighted sample, keeping the top-`k` by key `u^(1/weight)`.
+    pub fn insert_weighted(&mut self, x: f64, weight: f64) {
+        let heap = self
+            .weighted
+            .as_mut()
+            .expect("insert_weighted requires a weighted reservoir");
+        let key = self.rng.next_f64().powf(1.0 / weight);
+        let sample = Reverse(KeyedSample { key, value: x });
+        if heap.len() < self.capacity {
+            heap.push(sample);
+        } else if let Some(smallest) = heap.peek() {
+            if key > smallest.0.key {
+                heap.pop();
+                heap.push(sample);
+            }
+        }
+        self.seen += 1;
+    }
+
+    pub fn quantile(&self, q: f64, interp: QuantileInterpolation) -> f64 {
+        match &self.weighted {
+            Some(heap) => {
+                let values: Vec<f64> = heap.iter().map(|s| s.0.value).collect();
+                exact_quantile(&values, q, interp)
+            }
+            None => exact_quantile(&self.reservoir, q, interp),
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.seen
+    }
+}
+
+impl QuantileEstimator for ReservoirQuantileState {
+    fn insert(&mut self, x: f64) {
+        ReservoirQuantileState::insert(self, x);
+    }
+
+    fn quantile(&self, q: f64) -> f64 {
+        ReservoirQuantileState::quantile(self, q, QuantileInterpolation::Linear)
+    }
+
+    fn count(&self) -> usize {
+        self.seen
+    }
+
+    /// A reservoir cannot be merged without biasing the sample; reject any
+    /// attempt to combine a non-empty state rather than discard it silently.
+    fn merge(&mut self, other: &Self) {
+        assert!(
+            other.count() == 0,
+            "ReservoirQuantileState cannot be merged; sample the combined stream instead"
+        );
+    }
+}
+
+/// A weighted sample ordered by its key for the A-Res min-heap.
+#[derive(Debug)]
+struct KeyedSample {
+    key: f64,
+    value: f64,
+}
+
+impl PartialEq for KeyedSample {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl Eq for KeyedSample {}
+
+impl PartialOrd for KeyedSample {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for KeyedSample {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.total_cmp(&other.key)
+    }
+}
+
+/// A tiny xorshift64 PRNG, kept internal so the crate carries no RNG dependency
+/// on this path.
+#[derive(Debug)]
+struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self {
+            state: if seed == 0 { DEFAULT_SEED } else { seed },
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+        x
+    }
+
+    /// A uniform `f64` in `[0, 1)`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / ((1u64 << 53) as f64)
+    }
+
+    /// A uniform index in `0..n`.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn representative_median_in_fixed_memory() {
+        let mut state = ReservoirQuantileState::new_seeded(500, 42);
+        for i in 1..=1_000_000 {
+            state.insert(i as f64);
+        }
+        assert_eq!(state.count(), 1_000_000);
+        let p50 = state.quantile(0.5, QuantileInterpolation::Linear);
+        // Uniform sample of size 500 estimates the median to a few percent.
+        assert!((p50 - 500_000.0).abs() < 50_000.0, "p50 was {p50}");
+    }
+
+    #[test]
+    fn distinct_seeds_decorrelate_shards() {
+        let data: Vec<f64> = (1..=10_000).map(|i| i as f64).collect();
+        let mut a = ReservoirQuantileState::new_seeded(100, 1);
+        let mut b = ReservoirQuantileState::new_seeded(100, 2);
+        let mut a2 = ReservoirQuantileState::new_seeded(100, 1);
+        for &x in &data {
+            a.insert(x);
+            b.insert(x);
+            a2.insert(x);
+        }
+        let interp = QuantileInterpolation::Linear;
+        // Same seed is reproducible; distinct seeds draw different samples.
+        assert!((a.quantile(0.5, interp) - a2.quantile(0.5, interp)).abs() < 1e-9);
+        assert!((a.quantile(0.5, interp) - b.quantile(0.5, interp)).abs() > 1e-9);
+    }
+}
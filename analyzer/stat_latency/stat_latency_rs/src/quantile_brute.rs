@@ -1,17 +1,77 @@
+use std::cell::RefCell;
 use std::cmp::Ordering;
 
-fn exact_quantile(values: &[f64], q: f64) -> f64 {
+use crate::estimator::QuantileEstimator;
+use crate::stats::{QuantileInterpolation, StatError};
+
+/// Public entry point for [`exact_quantile`] under the name callers matching
+/// other tooling's "selectable interpolation method" wording tend to look
+/// for; `method` is [`QuantileInterpolation`] ("linear"/"lower"/"higher"/
+/// "nearest"/"midpoint"), matching numpy's corresponding methods.
+pub fn quantile_with(values: &[f64], q: f64, method: QuantileInterpolation) -> f64 {
+    exact_quantile(values, q, method)
+}
+
+/// Like [`quantile_with`] but failing loudly with [`StatError`] instead of
+/// silently returning `NaN` on empty `values` or an out-of-range `q` (checked
+/// before [`exact_quantile`]'s internal clamping would otherwise mask it).
+pub fn try_quantile_with(
+    values: &[f64],
+    q: f64,
+    method: QuantileInterpolation,
+) -> Result<f64, StatError> {
     if values.is_empty() {
+        return Err(StatError::Empty);
+    }
+    if !(0.0..=1.0).contains(&q) {
+        return Err(StatError::InvalidQuantile);
+    }
+    Ok(exact_quantile(values, q, method))
+}
+
+/// Result of [`BruteQuantileState::quantile_checked`]: a quantile estimate
+/// alongside whether it's backed by enough data to trust.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuantileResult {
+    pub value: f64,
+    /// `true` if the estimate collapsed onto `min`/`max` for lack of any
+    /// sample further into the requested tail, rather than because `q` was
+    /// actually `0.0`/`1.0`.
+    pub extrapolated: bool,
+    /// How many raw samples sit strictly beyond the requested rank, on the
+    /// far side from the median.
+    pub supporting_samples: usize,
+}
+
+/// Result of [`BruteQuantileState::quantile_detail`]: a quantile estimate
+/// alongside the two raw samples it was interpolated between, so a caller can
+/// explain exactly which data points a percentile came from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuantileDetail {
+    pub value: f64,
+    pub lower: f64,
+    pub upper: f64,
+    /// Fractional position of `value` between `lower` and `upper`, in
+    /// `[0.0, 1.0]`; `0.0` means `value == lower`, `1.0` means `value ==
+    /// upper`.
+    pub weight: f64,
+    pub lower_index: usize,
+    pub upper_index: usize,
+}
+
+/// Pick one quantile out of an already-sorted slice — the shared last step of
+/// [`exact_quantile`], [`exact_quantiles`], and [`BruteQuantileState`]'s
+/// cached queries, so all three agree on exactly how a fractional rank is
+/// resolved.
+fn quantile_from_sorted(sorted: &[f64], q: f64, interp: QuantileInterpolation) -> f64 {
+    if sorted.is_empty() {
         return f64::NAN;
     }
-    if values.len() == 1 {
-        return values[0];
+    if sorted.len() == 1 {
+        return sorted[0];
     }
 
     let q = q.clamp(0.0, 1.0);
-    let mut sorted = values.to_vec();
-    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
-
     let h = (sorted.len() - 1) as f64 * q;
     let lo = h.floor() as usize;
     let hi = h.ceil() as usize;
@@ -19,25 +79,1067 @@ fn exact_quantile(values: &[f64], q: f64) -> f64 {
         return sorted[lo];
     }
 
-    let w = h - (lo as f64);
-    sorted[lo] + (sorted[hi] - sorted[lo]) * w
+    interp.apply(sorted[lo], sorted[hi], h - (lo as f64))
+}
+
+pub(crate) fn exact_quantile(values: &[f64], q: f64, interp: QuantileInterpolation) -> f64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    quantile_from_sorted(&sorted, q, interp)
+}
+
+/// Like [`exact_quantile`] but for several `qs` at once: sorts `values` a
+/// single time and picks every quantile from that one sorted copy, instead of
+/// the `O(k * n log n)` cost of calling [`exact_quantile`] once per quantile.
+/// Each result matches what the corresponding individual `exact_quantile`
+/// call would return.
+pub(crate) fn exact_quantiles(values: &[f64], qs: &[f64], interp: QuantileInterpolation) -> Vec<f64> {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    qs.iter().map(|&q| quantile_from_sorted(&sorted, q, interp)).collect()
+}
+
+/// Default non-zero seed for [`BruteQuantileState::with_cap`]'s internal
+/// PRNG, giving reproducible downsampling across runs.
+const DEFAULT_DOWNSAMPLE_SEED: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// A tiny xorshift64 PRNG, kept internal so `with_cap` downsampling carries
+/// no external RNG dependency (mirrors the one in
+/// [`quantile_reservoir`](crate::quantile_reservoir)).
+#[derive(Debug)]
+struct DownsampleRng {
+    state: u64,
+}
+
+impl DownsampleRng {
+    fn new(seed: u64) -> Self {
+        Self {
+            state: if seed == 0 { DEFAULT_DOWNSAMPLE_SEED } else { seed },
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+        x
+    }
+
+    /// A uniform index in `0..n`.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
 }
 
 #[derive(Debug)]
 pub struct BruteQuantileState {
     values: Vec<f64>,
+    /// Lazily-rebuilt sorted copy of `values`, invalidated by every mutation.
+    /// A `RefCell` because building it is a cache-fill on an otherwise `&self`
+    /// query: interleaving many `quantile`/`quantile_many` calls between
+    /// inserts pays the `O(n log n)` sort once per batch of queries instead
+    /// of once per query, rather than the caching being externally visible.
+    sorted: RefCell<Option<Vec<f64>>>,
+    /// `Some(max)` once built via [`with_cap`](Self::with_cap): `values` is
+    /// bounded at `max` and downsampled with Algorithm R once exceeded.
+    /// `None` (the default, via [`new`](Self::new)) keeps every sample.
+    cap: Option<usize>,
+    /// Total inserts since the state was capped, used by Algorithm R's
+    /// replacement probability `cap / seen`.
+    seen: usize,
+    rng: Option<DownsampleRng>,
+    downsampled: bool,
 }
 
 impl BruteQuantileState {
     pub fn new() -> Self {
-        Self { values: Vec::new() }
+        Self {
+            values: Vec::new(),
+            sorted: RefCell::new(None),
+            cap: None,
+            seen: 0,
+            rng: None,
+            downsampled: false,
+        }
+    }
+
+    /// Bound memory at `max` samples: below the cap, behavior is byte-for-byte
+    /// identical to [`new`](Self::new); once `values` would exceed `max`,
+    /// further inserts uniformly replace an existing sample (Vitter's
+    /// Algorithm R, the same scheme as
+    /// [`ReservoirQuantileState`](crate::quantile_reservoir::ReservoirQuantileState))
+    /// so quantiles stay approximately correct instead of the state growing
+    /// unbounded. Uses a fixed internal seed; see
+    /// [`with_cap_seeded`](Self::with_cap_seeded) for deterministic tests.
+    pub fn with_cap(max: usize) -> Self {
+        Self::with_cap_seeded(max, DEFAULT_DOWNSAMPLE_SEED)
+    }
+
+    /// Like [`with_cap`](Self::with_cap) but with an explicit seed for the
+    /// downsampling PRNG.
+    pub fn with_cap_seeded(max: usize, seed: u64) -> Self {
+        Self {
+            values: Vec::with_capacity(max),
+            sorted: RefCell::new(None),
+            cap: Some(max),
+            seen: 0,
+            rng: Some(DownsampleRng::new(seed)),
+            downsampled: false,
+        }
+    }
+
+    /// Whether this state has ever discarded a sample to stay within its
+    /// [`with_cap`](Self::with_cap) bound, i.e. whether its quantiles are
+    /// only approximate. Always `false` for a [`new`](Self::new) state.
+    pub fn was_downsampled(&self) -> bool {
+        self.downsampled
     }
 
     pub fn insert(&mut self, x: f64) {
-        self.values.push(x);
+        match self.cap {
+            None => self.values.push(x),
+            Some(max) => {
+                if self.values.len() < max {
+                    self.values.push(x);
+                } else {
+                    self.downsampled = true;
+                    let rng = self.rng.as_mut().expect("with_cap always sets an rng");
+                    let j = rng.below(self.seen + 1);
+                    if j < max {
+                        self.values[j] = x;
+                    }
+                }
+                self.seen += 1;
+            }
+        }
+        *self.sorted.get_mut() = None;
+    }
+
+    /// Insert many values at once, avoiding the per-call overhead of
+    /// repeated [`insert`](Self::insert) calls. Uncapped states still get
+    /// the bulk `Vec::extend`; a capped state falls back to inserting one at
+    /// a time so downsampling sees every value.
+    pub fn extend(&mut self, values: impl IntoIterator<Item = f64>) {
+        if self.cap.is_none() {
+            self.values.extend(values);
+            *self.sorted.get_mut() = None;
+        } else {
+            for x in values {
+                self.insert(x);
+            }
+        }
+    }
+
+    /// Like [`insert`](Self::insert) but skipping NaN/+-inf samples (a failed
+    /// collector reading or a timeout placeholder), returning whether `x` was
+    /// accepted.
+    pub fn insert_checked(&mut self, x: f64) -> bool {
+        if x.is_finite() {
+            self.insert(x);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Rebuild the sorted cache if the last mutation invalidated it.
+    fn ensure_sorted(&self) {
+        if self.sorted.borrow().is_none() {
+            let mut sorted = self.values.clone();
+            sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+            *self.sorted.borrow_mut() = Some(sorted);
+        }
+    }
+
+    pub fn quantile(&self, q: f64, interp: QuantileInterpolation) -> f64 {
+        self.ensure_sorted();
+        quantile_from_sorted(self.sorted.borrow().as_ref().unwrap(), q, interp)
+    }
+
+    /// Like [`quantile`](Self::quantile) but reporting whether the answer is
+    /// actually backed by data out that far, instead of just landing on
+    /// `min`/`max` because too few samples exist to resolve a distinct tail
+    /// value. `supporting_samples` is how many raw samples sit strictly
+    /// beyond the requested rank, on the far side from the median — `0`
+    /// means the interpolation had nowhere left to go and collapsed onto an
+    /// extreme, so `extrapolated` is set. `q == 0.0`/`1.0` are never marked
+    /// extrapolated: `min`/`max` are exact regardless of sample count.
+    pub fn quantile_checked(&self, q: f64, interp: QuantileInterpolation) -> QuantileResult {
+        self.ensure_sorted();
+        let sorted = self.sorted.borrow();
+        let sorted = sorted.as_ref().unwrap();
+        if sorted.is_empty() {
+            return QuantileResult { value: f64::NAN, extrapolated: false, supporting_samples: 0 };
+        }
+        let value = quantile_from_sorted(sorted, q, interp);
+        if sorted.len() == 1 {
+            return QuantileResult { value, extrapolated: false, supporting_samples: 0 };
+        }
+
+        let clamped = q.clamp(0.0, 1.0);
+        let n = sorted.len();
+        let h = (n - 1) as f64 * clamped;
+        let lo = h.floor() as usize;
+        let hi = h.ceil() as usize;
+        let supporting_samples = if clamped >= 0.5 { n - 1 - hi } else { lo };
+        let extrapolated = clamped > 0.0 && clamped < 1.0 && supporting_samples == 0;
+        QuantileResult { value, extrapolated, supporting_samples }
     }
 
+    /// Like [`quantile_checked`](Self::quantile_checked) but reporting the raw
+    /// samples the value was interpolated between, instead of just whether
+    /// the estimate should be trusted — for explaining a surprising
+    /// percentile to a teammate by pointing at the actual bracketing data.
+    /// Always uses [`QuantileInterpolation::Linear`], matching
+    /// [`quantile_many`](Self::quantile_many)'s default. `lower_index` and
+    /// `upper_index` index into the sorted data (`lower_index == upper_index`
+    /// when the rank lands exactly on a sample). `NaN`/zeroed on an empty
+    /// state; `lower == upper == the single value` on a one-element state.
+    pub fn quantile_detail(&self, q: f64) -> QuantileDetail {
+        self.ensure_sorted();
+        let sorted = self.sorted.borrow();
+        let sorted = sorted.as_ref().unwrap();
+        if sorted.is_empty() {
+            return QuantileDetail {
+                value: f64::NAN,
+                lower: f64::NAN,
+                upper: f64::NAN,
+                weight: f64::NAN,
+                lower_index: 0,
+                upper_index: 0,
+            };
+        }
+        if sorted.len() == 1 {
+            return QuantileDetail {
+                value: sorted[0],
+                lower: sorted[0],
+                upper: sorted[0],
+                weight: 0.0,
+                lower_index: 0,
+                upper_index: 0,
+            };
+        }
+
+        let clamped = q.clamp(0.0, 1.0);
+        let n = sorted.len();
+        let h = (n - 1) as f64 * clamped;
+        let lo = h.floor() as usize;
+        let hi = h.ceil() as usize;
+        let value = quantile_from_sorted(sorted, q, QuantileInterpolation::Linear);
+        QuantileDetail {
+            value,
+            lower: sorted[lo],
+            upper: sorted[hi],
+            weight: h - lo as f64,
+            lower_index: lo,
+            upper_index: hi,
+        }
+    }
+
+    /// [`quantile`](Self::quantile) for every `q` in `qs`, reusing the sorted
+    /// cache instead of sorting once per quantile — the fix for the common
+    /// "compute the whole percentile grid" path.
+    pub fn quantile_many(&self, qs: &[f64]) -> Vec<f64> {
+        self.ensure_sorted();
+        let sorted = self.sorted.borrow();
+        let sorted = sorted.as_ref().unwrap();
+        qs.iter().map(|&q| quantile_from_sorted(sorted, q, QuantileInterpolation::Linear)).collect()
+    }
+
+    /// Drop every stored value so this state can be reused for the next
+    /// time window instead of allocating a fresh one.
+    pub fn clear(&mut self) {
+        self.values.clear();
+        self.seen = 0;
+        self.downsampled = false;
+        *self.sorted.get_mut() = None;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Approximate heap footprint in bytes: `values`' allocated capacity plus
+    /// the lazily-rebuilt sorted cache's, when it's currently populated. Uses
+    /// `capacity()`, not `len()`, since that's what's actually resident —
+    /// meaningful for a coordinator deciding when to flush/serialize a state
+    /// against a real memory budget rather than guessing by sample count.
+    pub fn memory_bytes(&self) -> usize {
+        let values_bytes = self.values.capacity() * std::mem::size_of::<f64>();
+        let sorted_bytes = self
+            .sorted
+            .borrow()
+            .as_ref()
+            .map_or(0, |s| s.capacity() * std::mem::size_of::<f64>());
+        std::mem::size_of::<Self>() + values_bytes + sorted_bytes
+    }
+
+    /// A sorted copy of the raw samples, for callers that want to inspect or
+    /// export the underlying distribution rather than just query quantiles
+    /// of it. Unlike the internal sort cache used by
+    /// [`quantile`](Self::quantile), which treats NaN as equal to its
+    /// neighbours wherever `partial_cmp` fails, this orders NaN samples to
+    /// the end deterministically so the result is a well-defined total
+    /// order regardless of insertion history.
+    pub fn sorted_values(&self) -> Vec<f64> {
+        let mut sorted = self.values.clone();
+        sorted.sort_by(Self::cmp_nan_last);
+        sorted
+    }
+
+    /// Like [`sorted_values`](Self::sorted_values) but consumes `self`,
+    /// avoiding the copy for callers done with the state.
+    pub fn into_sorted_vec(self) -> Vec<f64> {
+        let mut values = self.values;
+        values.sort_by(Self::cmp_nan_last);
+        values
+    }
+
+    /// Total order over `f64` that places NaN after every other value,
+    /// including `+inf`, so a sort using it is deterministic even when NaN
+    /// samples are present.
+    fn cmp_nan_last(a: &f64, b: &f64) -> Ordering {
+        match a.partial_cmp(b) {
+            Some(ord) => ord,
+            None => match (a.is_nan(), b.is_nan()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => unreachable!("partial_cmp only fails on NaN"),
+            },
+        }
+    }
+
+    /// Alias for [`rank_le`](Self::rank_le): with heavy duplicate values, "the
+    /// fraction of samples at or below `value`" is the definition most SLA
+    /// reports want ("did this request finish within threshold?" is an `<=`
+    /// question), so that's what the plain, unqualified name gives you. Reach
+    /// for [`rank_lt`](Self::rank_lt) or [`rank_mid`](Self::rank_mid)
+    /// explicitly when a report needs one of those instead.
+    pub fn rank(&self, value: f64) -> f64 {
+        self.rank_le(value)
+    }
+
+    /// The fraction of samples `<= value`. On data with many samples exactly
+    /// equal to `value`, this counts all of them, so a request landing
+    /// exactly at an SLA threshold "passes" it.
+    pub fn rank_le(&self, value: f64) -> f64 {
+        if self.values.is_empty() {
+            return f64::NAN;
+        }
+        self.ensure_sorted();
+        let sorted = self.sorted.borrow();
+        let sorted = sorted.as_ref().unwrap();
+        // `partition_point` binary-searches for the first element greater
+        // than `value`, which is exactly the count of elements `<= value`.
+        let count_le = sorted.partition_point(|&x| x <= value);
+        count_le as f64 / sorted.len() as f64
+    }
+
+    /// The fraction of samples `< value`. On data with many samples exactly
+    /// equal to `value`, none of them count, so a request landing exactly at
+    /// an SLA threshold does not count as beating it.
+    pub fn rank_lt(&self, value: f64) -> f64 {
+        if self.values.is_empty() {
+            return f64::NAN;
+        }
+        self.ensure_sorted();
+        let sorted = self.sorted.borrow();
+        let sorted = sorted.as_ref().unwrap();
+        // `partition_point` binary-searches for the first element `>= value`,
+        // which is exactly the count of elements `< value`.
+        let count_lt = sorted.partition_point(|&x| x < value);
+        count_lt as f64 / sorted.len() as f64
+    }
+
+    /// The midpoint rank: the mean of [`rank_lt`](Self::rank_lt) and
+    /// [`rank_le`](Self::rank_le), splitting the samples tied at exactly
+    /// `value` evenly between "below" and "at or below" rather than handing
+    /// them entirely to one side. This is the rank convention some
+    /// statistics packages (e.g. `scipy.stats.percentileofscore` with
+    /// `kind="mean"`) use for heavily-duplicated data.
+    pub fn rank_mid(&self, value: f64) -> f64 {
+        if self.values.is_empty() {
+            return f64::NAN;
+        }
+        (self.rank_lt(value) + self.rank_le(value)) / 2.0
+    }
+
+    /// Fold `other`'s samples into `self`. The exact quantiles afterwards
+    /// equal computing over the concatenation of both inputs, unless `self`
+    /// is capped, in which case `other`'s samples go through the same
+    /// downsampling as any other insert.
+    pub fn merge(&mut self, other: &BruteQuantileState) {
+        if self.cap.is_none() {
+            self.values.extend_from_slice(&other.values);
+            *self.sorted.get_mut() = None;
+        } else {
+            for &x in &other.values {
+                self.insert(x);
+            }
+        }
+    }
+
+    /// Combine many per-worker states into one, e.g. folding every node's
+    /// `BruteQuantileState` into a single global estimator before reporting.
+    pub fn merge_from(states: impl IntoIterator<Item = BruteQuantileState>) -> BruteQuantileState {
+        let mut merged = BruteQuantileState::new();
+        for state in states {
+            merged.values.extend(state.values);
+        }
+        merged
+    }
+}
+
+impl Default for BruteQuantileState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A (value, weight) pair for [`WeightedBruteQuantileState`], e.g. one sample
+/// per latency bucket with the bucket's hit count as its weight instead of
+/// replaying every individual hit.
+#[derive(Debug, Clone, Copy)]
+struct WeightedSample {
+    value: f64,
+    weight: f64,
+}
+
+/// Exact quantiles over weighted samples, linearly interpolated between the
+/// bracketing samples exactly like [`BruteQuantileState::quantile`]'s
+/// [`QuantileInterpolation::Linear`]: on equal weights the two agree exactly,
+/// since a weighted sample is just an unweighted one with its cumulative
+/// mass spread wider.
+#[derive(Debug, Default)]
+pub struct WeightedBruteQuantileState {
+    samples: Vec<WeightedSample>,
+    total_weight: f64,
+}
+
+impl WeightedBruteQuantileState {
+    pub fn new() -> Self {
+        Self {
+            samples: Vec::new(),
+            total_weight: 0.0,
+        }
+    }
+
+    /// Insert `value` with `weight`. Zero and negative weights are rejected
+    /// (returning `false`) since they would mean the value counts backwards
+    /// or not at all towards the cumulative-weight rank.
+    pub fn insert(&mut self, value: f64, weight: f64) -> bool {
+        if !(weight > 0.0) || !value.is_finite() {
+            return false;
+        }
+        self.samples.push(WeightedSample { value, weight });
+        self.total_weight += weight;
+        true
+    }
+
+    /// The value at cumulative weight share `q` (clamped to `[0, 1]`),
+    /// linearly interpolated between the two bracketing samples. Each sorted
+    /// sample is placed at the midpoint of its share of the cumulative
+    /// weight, normalized so the first sample sits at `q = 0.0` and the last
+    /// at `q = 1.0` — the weighted generalization of
+    /// [`BruteQuantileState`]'s `h = (n - 1) * q` index math, which this
+    /// reduces to exactly when every weight is equal.
     pub fn quantile(&self, q: f64) -> f64 {
-        exact_quantile(&self.values, q)
+        if self.samples.is_empty() {
+            return f64::NAN;
+        }
+        let q = q.clamp(0.0, 1.0);
+        let mut sorted = self.samples.clone();
+        sorted.sort_by(|a, b| a.value.partial_cmp(&b.value).unwrap_or(Ordering::Equal));
+        let n = sorted.len();
+        if n == 1 {
+            return sorted[0].value;
+        }
+
+        let mut cumulative = 0.0;
+        let mut positions = Vec::with_capacity(n);
+        for s in &sorted {
+            positions.push(cumulative + s.weight / 2.0);
+            cumulative += s.weight;
+        }
+        let span = positions[n - 1] - positions[0];
+        if span <= 0.0 {
+            return sorted[0].value;
+        }
+        let target = positions[0] + q * span;
+
+        for i in 1..n {
+            if target <= positions[i] {
+                let w = (target - positions[i - 1]) / (positions[i] - positions[i - 1]);
+                return sorted[i - 1].value + (sorted[i].value - sorted[i - 1].value) * w;
+            }
+        }
+        sorted[n - 1].value
+    }
+
+    /// Number of `insert` calls accepted (not the sum of weights).
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn total_weight(&self) -> f64 {
+        self.total_weight
+    }
+}
+
+impl QuantileEstimator for BruteQuantileState {
+    fn insert(&mut self, x: f64) {
+        BruteQuantileState::insert(self, x);
+    }
+
+    fn quantile(&self, q: f64) -> f64 {
+        BruteQuantileState::quantile(self, q, QuantileInterpolation::Linear)
+    }
+
+    fn count(&self) -> usize {
+        self.values.len()
+    }
+
+    fn merge(&mut self, other: &Self) {
+        BruteQuantileState::merge(self, other);
+    }
+}
+
+/// Delegates to the inherent [`extend`](BruteQuantileState::extend), so
+/// `state.extend(samples)` keeps working whether or not `std::iter::Extend`
+/// is in scope, and generic code written against `Extend<f64>` can target
+/// this state too.
+impl Extend<f64> for BruteQuantileState {
+    fn extend<I: IntoIterator<Item = f64>>(&mut self, iter: I) {
+        BruteQuantileState::extend(self, iter);
+    }
+}
+
+/// Lets `samples.into_iter().collect::<BruteQuantileState>()` build a state
+/// directly, for callers who already have an iterator and don't want to
+/// spell out `BruteQuantileState::new()` plus a separate `extend` call.
+impl FromIterator<f64> for BruteQuantileState {
+    fn from_iter<I: IntoIterator<Item = f64>>(iter: I) -> Self {
+        let mut state = BruteQuantileState::new();
+        state.extend(iter);
+        state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantile_many_matches_individual_quantile_calls() {
+        let mut state = BruteQuantileState::new();
+        for i in 1..=1000 {
+            state.insert(i as f64);
+        }
+        let qs = [0.0, 0.1, 0.5, 0.9, 0.99, 1.0];
+        let batch = state.quantile_many(&qs);
+        let individual: Vec<f64> = qs
+            .iter()
+            .map(|&q| state.quantile(q, QuantileInterpolation::Linear))
+            .collect();
+        assert_eq!(batch, individual);
+    }
+
+    #[test]
+    fn quantile_checked_flags_extrapolation_on_a_tiny_sample() {
+        let mut state = BruteQuantileState::new();
+        for i in 1..=7 {
+            state.insert(i as f64);
+        }
+        let result = state.quantile_checked(0.999, QuantileInterpolation::Linear);
+        // h = (n - 1) * q = 5.994, so linear interpolation lands just short
+        // of the max rather than exactly on it.
+        assert!((result.value - 7.0).abs() < 0.01, "p999 of 7 samples nearly collapses to the max, got {}", result.value);
+        assert!(result.extrapolated);
+        assert_eq!(result.supporting_samples, 0);
+    }
+
+    #[test]
+    fn quantile_checked_is_well_supported_with_plenty_of_data() {
+        let mut state = BruteQuantileState::new();
+        for i in 1..=1000 {
+            state.insert(i as f64);
+        }
+        let result = state.quantile_checked(0.5, QuantileInterpolation::Linear);
+        assert!(!result.extrapolated);
+        assert!(result.supporting_samples > 0);
+    }
+
+    #[test]
+    fn quantile_checked_never_marks_exact_min_or_max_as_extrapolated() {
+        let mut state = BruteQuantileState::new();
+        for i in 1..=7 {
+            state.insert(i as f64);
+        }
+        assert!(!state.quantile_checked(0.0, QuantileInterpolation::Linear).extrapolated);
+        assert!(!state.quantile_checked(1.0, QuantileInterpolation::Linear).extrapolated);
+    }
+
+    #[test]
+    fn quantile_checked_is_nan_and_not_extrapolated_on_empty_state() {
+        let state = BruteQuantileState::new();
+        let result = state.quantile_checked(0.5, QuantileInterpolation::Linear);
+        assert!(result.value.is_nan());
+        assert!(!result.extrapolated);
+        assert_eq!(result.supporting_samples, 0);
+    }
+
+    #[test]
+    fn quantile_detail_reports_the_bracketing_samples_it_interpolated_between() {
+        let mut state = BruteQuantileState::new();
+        for i in 1..=5 {
+            state.insert(i as f64);
+        }
+        // p90 of [1,2,3,4,5]: h = 4 * 0.9 = 3.6, so it's interpolated 60% of
+        // the way from index 3 (value 4) to index 4 (value 5).
+        let detail = state.quantile_detail(0.9);
+        assert_eq!(detail.value, 4.6);
+        assert_eq!(detail.lower, 4.0);
+        assert_eq!(detail.upper, 5.0);
+        assert_eq!(detail.lower_index, 3);
+        assert_eq!(detail.upper_index, 4);
+        assert!((detail.weight - 0.6).abs() < 1e-9);
+    }
+
+    #[test]
+    fn quantile_detail_collapses_lower_and_upper_when_the_rank_lands_exactly_on_a_sample() {
+        let mut state = BruteQuantileState::new();
+        for i in 1..=5 {
+            state.insert(i as f64);
+        }
+        let detail = state.quantile_detail(0.5);
+        assert_eq!(detail.value, 3.0);
+        assert_eq!(detail.lower, 3.0);
+        assert_eq!(detail.upper, 3.0);
+        assert_eq!(detail.lower_index, 2);
+        assert_eq!(detail.upper_index, 2);
+        assert_eq!(detail.weight, 0.0);
+    }
+
+    #[test]
+    fn quantile_detail_is_degenerate_on_a_single_sample() {
+        let mut state = BruteQuantileState::new();
+        state.insert(42.0);
+        let detail = state.quantile_detail(0.99);
+        assert_eq!(detail.value, 42.0);
+        assert_eq!(detail.lower, 42.0);
+        assert_eq!(detail.upper, 42.0);
+        assert_eq!(detail.lower_index, 0);
+        assert_eq!(detail.upper_index, 0);
+    }
+
+    #[test]
+    fn quantile_detail_is_nan_on_empty_state() {
+        let state = BruteQuantileState::new();
+        let detail = state.quantile_detail(0.5);
+        assert!(detail.value.is_nan());
+        assert!(detail.lower.is_nan());
+        assert!(detail.upper.is_nan());
+    }
+
+    #[test]
+    fn exact_quantiles_matches_repeated_exact_quantile_including_edge_cases() {
+        assert!(exact_quantiles(&[], &[0.5, 0.9], QuantileInterpolation::Linear).iter().all(|v| v.is_nan()));
+        assert_eq!(exact_quantiles(&[42.0], &[0.0, 1.0], QuantileInterpolation::Linear), vec![42.0, 42.0]);
+
+        let values = vec![5.0, 1.0, 3.0, 2.0, 4.0];
+        let qs = [0.0, 0.25, 0.5, 0.75, 1.0];
+        let batch = exact_quantiles(&values, &qs, QuantileInterpolation::Nearest);
+        let individual: Vec<f64> = qs
+            .iter()
+            .map(|&q| exact_quantile(&values, q, QuantileInterpolation::Nearest))
+            .collect();
+        assert_eq!(batch, individual);
+    }
+
+    #[test]
+    fn recovers_exact_quantiles() {
+        let mut state = BruteQuantileState::new();
+        for i in 1..=100 {
+            state.insert(i as f64);
+        }
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+        assert!(close(state.quantile(0.5, QuantileInterpolation::Linear), 50.5));
+        assert!(close(state.quantile(0.5, QuantileInterpolation::Lower), 50.0));
+        assert!(close(state.quantile(0.5, QuantileInterpolation::Higher), 51.0));
+        assert!(close(state.quantile(0.5, QuantileInterpolation::Nearest), 50.0));
+        assert!(close(state.quantile(0.5, QuantileInterpolation::Midpoint), 50.5));
+    }
+
+    #[test]
+    fn interleaved_inserts_and_queries_match_naive_recompute() {
+        let mut cached = BruteQuantileState::new();
+        let mut naive: Vec<f64> = Vec::new();
+        for i in 1..=3_000u32 {
+            cached.insert(i as f64);
+            naive.push(i as f64);
+            if i % 7 == 0 {
+                // Multiple queries in a row between inserts must all see the
+                // same cached sort, and it must be rebuilt after the next insert.
+                for &q in &[0.0, 0.1, 0.5, 0.9, 0.99, 1.0] {
+                    let expected = exact_quantile(&naive, q, QuantileInterpolation::Linear);
+                    assert_eq!(cached.quantile(q, QuantileInterpolation::Linear), expected);
+                }
+            }
+        }
+        assert_eq!(cached.len(), naive.len());
+    }
+
+    #[test]
+    fn try_quantile_with_matches_quantile_with_on_valid_input() {
+        let values: Vec<f64> = (1..=100).map(|i| i as f64).collect();
+        assert_eq!(
+            try_quantile_with(&values, 0.5, QuantileInterpolation::Linear).unwrap(),
+            quantile_with(&values, 0.5, QuantileInterpolation::Linear)
+        );
+    }
+
+    #[test]
+    fn try_quantile_with_rejects_empty_and_out_of_range_q() {
+        assert_eq!(
+            try_quantile_with(&[], 0.5, QuantileInterpolation::Linear),
+            Err(crate::stats::StatError::Empty)
+        );
+        assert_eq!(
+            try_quantile_with(&[1.0, 2.0], 1.5, QuantileInterpolation::Linear),
+            Err(crate::stats::StatError::InvalidQuantile)
+        );
+    }
+
+    #[test]
+    fn quantile_with_matches_state_quantile() {
+        let mut state = BruteQuantileState::new();
+        for i in 1..=100 {
+            state.insert(i as f64);
+        }
+        let values: Vec<f64> = (1..=100).map(|i| i as f64).collect();
+        assert_eq!(
+            quantile_with(&values, 0.5, QuantileInterpolation::Nearest),
+            state.quantile(0.5, QuantileInterpolation::Nearest)
+        );
+    }
+
+    #[test]
+    fn extend_matches_repeated_insert() {
+        let mut one_by_one = BruteQuantileState::new();
+        for i in 1..=200 {
+            one_by_one.insert(i as f64);
+        }
+        let mut extended = BruteQuantileState::new();
+        extended.extend((1..=200).map(|i| i as f64));
+        assert_eq!(
+            one_by_one.quantile(0.5, QuantileInterpolation::Linear),
+            extended.quantile(0.5, QuantileInterpolation::Linear)
+        );
+        assert_eq!(extended.len(), 200);
+    }
+
+    #[test]
+    fn from_iterator_collects_like_new_plus_extend() {
+        let collected: BruteQuantileState = (1..=200).map(|i| i as f64).collect();
+        let mut built = BruteQuantileState::new();
+        built.extend((1..=200).map(|i| i as f64));
+        assert_eq!(collected.len(), built.len());
+        assert_eq!(
+            collected.quantile(0.5, QuantileInterpolation::Linear),
+            built.quantile(0.5, QuantileInterpolation::Linear)
+        );
+    }
+
+    #[test]
+    fn extend_trait_matches_inherent_extend() {
+        let mut via_trait = BruteQuantileState::new();
+        Extend::extend(&mut via_trait, (1..=200).map(|i| i as f64));
+        let mut via_inherent = BruteQuantileState::new();
+        via_inherent.extend((1..=200).map(|i| i as f64));
+        assert_eq!(via_trait.len(), via_inherent.len());
+        assert_eq!(
+            via_trait.quantile(0.9, QuantileInterpolation::Linear),
+            via_inherent.quantile(0.9, QuantileInterpolation::Linear)
+        );
+    }
+
+    #[test]
+    fn extend_trait_respects_cap_downsampling() {
+        let mut capped = BruteQuantileState::with_cap_seeded(50, 7);
+        Extend::extend(&mut capped, (1..=1000).map(|i| i as f64));
+        assert!(capped.was_downsampled());
+        assert!(capped.len() <= 50);
+    }
+
+    #[test]
+    fn clear_resets_to_empty() {
+        let mut state = BruteQuantileState::new();
+        state.insert(1.0);
+        state.insert(2.0);
+        assert!(!state.is_empty());
+        assert_eq!(state.len(), 2);
+        state.clear();
+        assert!(state.is_empty());
+        assert_eq!(state.len(), 0);
+    }
+
+    #[test]
+    fn memory_bytes_grows_linearly_with_inserts() {
+        let mut small = BruteQuantileState::new();
+        for i in 0..100 {
+            small.insert(i as f64);
+        }
+        let mut large = BruteQuantileState::new();
+        for i in 0..10_000 {
+            large.insert(i as f64);
+        }
+        // Not exact equality (capacity growth isn't 1:1 with len), just that
+        // 100x more samples costs meaningfully more, not a constant amount.
+        assert!(large.memory_bytes() > small.memory_bytes() * 10);
+    }
+
+    #[test]
+    fn memory_bytes_is_nonzero_even_on_an_empty_state() {
+        let state = BruteQuantileState::new();
+        assert!(state.memory_bytes() > 0);
+    }
+
+    #[test]
+    fn sorted_values_is_sorted_and_preserves_all_samples() {
+        let mut state = BruteQuantileState::new();
+        for &x in &[5.0, 1.0, 4.0, 2.0, 3.0] {
+            state.insert(x);
+        }
+        assert_eq!(state.sorted_values(), vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+        // A copy: the state is left untouched.
+        assert_eq!(state.len(), 5);
+    }
+
+    #[test]
+    fn sorted_values_puts_nan_last() {
+        let mut state = BruteQuantileState::new();
+        for &x in &[3.0, f64::NAN, 1.0, f64::NAN, 2.0] {
+            state.insert(x);
+        }
+        let sorted = state.sorted_values();
+        assert_eq!(&sorted[..3], &[1.0, 2.0, 3.0]);
+        assert!(sorted[3].is_nan());
+        assert!(sorted[4].is_nan());
+    }
+
+    #[test]
+    fn into_sorted_vec_matches_sorted_values() {
+        let mut state = BruteQuantileState::new();
+        for &x in &[5.0, 1.0, 4.0, 2.0, 3.0] {
+            state.insert(x);
+        }
+        let via_copy = state.sorted_values();
+        let via_consume = state.into_sorted_vec();
+        assert_eq!(via_copy, via_consume);
+    }
+
+    #[test]
+    fn rank_is_inverse_of_quantile() {
+        let mut state = BruteQuantileState::new();
+        for i in 1..=100 {
+            state.insert(i as f64);
+        }
+        assert!((state.rank(50.0) - 0.5).abs() < 1e-9);
+        assert_eq!(state.rank(-100.0), 0.0);
+        assert_eq!(state.rank(1000.0), 1.0);
+        assert!(BruteQuantileState::new().rank(1.0).is_nan());
+    }
+
+    #[test]
+    fn rank_is_an_alias_for_rank_le() {
+        let mut state = BruteQuantileState::new();
+        for &x in &[1.0, 2.0, 2.0, 2.0, 3.0] {
+            state.insert(x);
+        }
+        assert_eq!(state.rank(2.0), state.rank_le(2.0));
+    }
+
+    #[test]
+    fn rank_le_and_rank_lt_disagree_on_heavy_duplicates() {
+        let mut state = BruteQuantileState::new();
+        for &x in &[1.0, 2.0, 2.0, 2.0, 2.0, 3.0] {
+            state.insert(x);
+        }
+        // 5 of 6 samples are <= 2.0 (everything but the 3.0); only 1 of 6 is
+        // strictly < 2.0 (just the 1.0).
+        assert!((state.rank_le(2.0) - 5.0 / 6.0).abs() < 1e-9);
+        assert!((state.rank_lt(2.0) - 1.0 / 6.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn rank_mid_splits_the_difference() {
+        let mut state = BruteQuantileState::new();
+        for &x in &[1.0, 2.0, 2.0, 2.0, 2.0, 3.0] {
+            state.insert(x);
+        }
+        let expected = (state.rank_lt(2.0) + state.rank_le(2.0)) / 2.0;
+        assert!((state.rank_mid(2.0) - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn rank_variants_agree_without_duplicates() {
+        let mut state = BruteQuantileState::new();
+        for i in 1..=100 {
+            state.insert(i as f64);
+        }
+        // With no duplicate at 50.0, "<= 50.0" and "< 50.0" differ by exactly
+        // one sample's worth of rank, and the midpoint rank sits between them.
+        assert!((state.rank_le(50.0) - 0.5).abs() < 1e-9);
+        assert!((state.rank_lt(50.0) - 0.49).abs() < 1e-9);
+        assert!(state.rank_mid(50.0) > state.rank_lt(50.0));
+        assert!(state.rank_mid(50.0) < state.rank_le(50.0));
+    }
+
+    #[test]
+    fn rank_variants_are_nan_on_empty_state() {
+        let state = BruteQuantileState::new();
+        assert!(state.rank_le(1.0).is_nan());
+        assert!(state.rank_lt(1.0).is_nan());
+        assert!(state.rank_mid(1.0).is_nan());
+    }
+
+    #[test]
+    fn insert_checked_rejects_non_finite() {
+        let mut state = BruteQuantileState::new();
+        assert!(state.insert_checked(1.0));
+        assert!(!state.insert_checked(f64::NAN));
+        assert!(!state.insert_checked(f64::INFINITY));
+        assert_eq!(state.count(), 1);
+    }
+
+    #[test]
+    fn merge_matches_concatenation() {
+        let mut whole = BruteQuantileState::new();
+        let mut left = BruteQuantileState::new();
+        let mut right = BruteQuantileState::new();
+        for i in 1..=200 {
+            whole.insert(i as f64);
+            if i <= 120 {
+                left.insert(i as f64);
+            } else {
+                right.insert(i as f64);
+            }
+        }
+        left.merge(&right);
+        assert_eq!(
+            left.quantile(0.5, QuantileInterpolation::Linear),
+            whole.quantile(0.5, QuantileInterpolation::Linear)
+        );
+        assert_eq!(
+            left.quantile(0.99, QuantileInterpolation::Linear),
+            whole.quantile(0.99, QuantileInterpolation::Linear)
+        );
+    }
+
+    #[test]
+    fn weighted_matches_unweighted_when_all_weights_equal() {
+        let mut unweighted = BruteQuantileState::new();
+        let mut weighted = WeightedBruteQuantileState::new();
+        for i in 1..=100 {
+            unweighted.insert(i as f64);
+            assert!(weighted.insert(i as f64, 1.0));
+        }
+        assert_eq!(
+            unweighted.quantile(0.5, QuantileInterpolation::Linear),
+            weighted.quantile(0.5)
+        );
+    }
+
+    #[test]
+    fn weighted_quantile_reflects_bucket_weights() {
+        let mut weighted = WeightedBruteQuantileState::new();
+        weighted.insert(1.0, 90.0);
+        weighted.insert(100.0, 10.0);
+        // The two samples sit at cumulative-weight midpoints 45 and 95 (out of
+        // 100 total), so q=0.5 (target 70) is 50% of the way between them and
+        // q=0.99 (target 94.5) is 99% of the way there.
+        let p50 = weighted.quantile(0.5);
+        assert!((p50 - 50.5).abs() < 1e-9, "p50 was {p50}");
+        let p99 = weighted.quantile(0.99);
+        assert!((p99 - 99.01).abs() < 1e-9, "p99 was {p99}");
+        assert_eq!(weighted.total_weight(), 100.0);
+    }
+
+    #[test]
+    fn weighted_rejects_non_positive_weights() {
+        let mut weighted = WeightedBruteQuantileState::new();
+        assert!(!weighted.insert(1.0, 0.0));
+        assert!(!weighted.insert(1.0, -5.0));
+        assert!(weighted.is_empty());
+        assert_eq!(weighted.len(), 0);
+    }
+
+    #[test]
+    fn with_cap_matches_new_below_the_cap() {
+        let mut capped = BruteQuantileState::with_cap_seeded(1_000, 1);
+        let mut uncapped = BruteQuantileState::new();
+        for i in 1..=500 {
+            capped.insert(i as f64);
+            uncapped.insert(i as f64);
+        }
+        assert!(!capped.was_downsampled());
+        assert_eq!(capped.len(), uncapped.len());
+        assert_eq!(
+            capped.quantile(0.5, QuantileInterpolation::Linear),
+            uncapped.quantile(0.5, QuantileInterpolation::Linear)
+        );
+    }
+
+    #[test]
+    fn with_cap_bounds_memory_and_stays_approximately_correct() {
+        let mut capped = BruteQuantileState::with_cap_seeded(500, 42);
+        for i in 1..=1_000_000 {
+            capped.insert(i as f64);
+        }
+        assert!(capped.was_downsampled());
+        assert_eq!(capped.len(), 500);
+        let p50 = capped.quantile(0.5, QuantileInterpolation::Linear);
+        assert!((p50 - 500_000.0).abs() < 50_000.0, "p50 was {p50}");
+    }
+
+    #[test]
+    fn with_cap_downsampling_is_deterministic_for_a_fixed_seed() {
+        let mut a = BruteQuantileState::with_cap_seeded(100, 7);
+        let mut b = BruteQuantileState::with_cap_seeded(100, 7);
+        for i in 1..=10_000 {
+            a.insert(i as f64);
+            b.insert(i as f64);
+        }
+        assert_eq!(
+            a.quantile(0.5, QuantileInterpolation::Linear),
+            b.quantile(0.5, QuantileInterpolation::Linear)
+        );
+    }
+
+    #[test]
+    fn merge_from_combines_per_node_states() {
+        let nodes: Vec<BruteQuantileState> = (0..4)
+            .map(|n| {
+                let mut s = BruteQuantileState::new();
+                for i in 1..=50 {
+                    s.insert((n * 50 + i) as f64);
+                }
+                s
+            })
+            .collect();
+        let merged = BruteQuantileState::merge_from(nodes);
+        assert_eq!(merged.quantile(1.0, QuantileInterpolation::Higher), 200.0);
+        assert_eq!(merged.quantile(0.0, QuantileInterpolation::Lower), 1.0);
     }
 }
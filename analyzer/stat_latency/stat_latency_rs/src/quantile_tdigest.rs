@@ -1,9 +1,15 @@
-use tdigests::TDigest;
+use serde::de::Deserializer;
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+use tdigests::{Centroid, TDigest};
+
+use crate::estimator::QuantileEstimator;
 
 #[derive(Debug)]
 pub struct TDigestQuantileState {
     digest: Option<TDigest>,
     buffer: Vec<f64>,
+    count: usize,
 }
 
 impl TDigestQuantileState {
@@ -11,11 +17,13 @@ impl TDigestQuantileState {
         Self {
             digest: None,
             buffer: vec![],
+            count: 0,
         }
     }
 
     pub fn insert(&mut self, x: f64) {
         self.buffer.push(x);
+        self.count += 1;
         if self.buffer.len() >= 200 {
             self.merge();
         }
@@ -42,4 +50,146 @@ impl TDigestQuantileState {
             .map(|d| d.estimate_quantile(q))
             .unwrap_or(f64::NAN)
     }
+
+    /// Snapshot the state as a flat `(mean, weight)` centroid list. Any samples
+    /// still in the buffer are emitted as unit-weight centroids so the snapshot
+    /// is complete without mutating `self`.
+    pub fn to_centroids(&self) -> Vec<(f64, f64)> {
+        let mut out: Vec<(f64, f64)> = self
+            .digest
+            .as_ref()
+            .map(|d| d.centroids().iter().map(|c| (c.mean(), c.weight())).collect())
+            .unwrap_or_default();
+        out.extend(self.buffer.iter().map(|&v| (v, 1.0)));
+        out
+    }
+
+    /// Rebuild a state from a centroid list and its total sample count,
+    /// re-compressing to the 2000-centroid bound.
+    pub fn from_centroids(centroids: Vec<(f64, f64)>, count: usize) -> Self {
+        let digest = if centroids.is_empty() {
+            None
+        } else {
+            let cs = centroids
+                .into_iter()
+                .map(|(mean, weight)| Centroid::new(mean, weight))
+                .collect();
+            let mut d = TDigest::from_centroids(cs);
+            d.compress(2000);
+            Some(d)
+        };
+        Self {
+            digest,
+            buffer: vec![],
+            count,
+        }
+    }
+
+    /// Materialize this state (digest plus any buffered samples) into a single
+    /// `TDigest`, without disturbing `self`.
+    fn as_digest(&self) -> Option<TDigest> {
+        let centroids = self.to_centroids();
+        if centroids.is_empty() {
+            return None;
+        }
+        let cs = centroids
+            .into_iter()
+            .map(|(mean, weight)| Centroid::new(mean, weight))
+            .collect();
+        Some(TDigest::from_centroids(cs))
+    }
+
+    /// Combine another worker's digest into this one: flush both buffers, merge
+    /// the underlying `TDigest`s, and re-compress to the 2000-centroid bound.
+    pub fn merge_state(&mut self, other: &TDigestQuantileState) {
+        self.merge();
+        let combined = match (self.digest.take(), other.as_digest()) {
+            (Some(a), Some(b)) => Some(a.merge(&b)),
+            (Some(a), None) => Some(a),
+            (None, Some(b)) => Some(b),
+            (None, None) => None,
+        };
+        if let Some(mut d) = combined {
+            d.compress(2000);
+            self.digest = Some(d);
+        }
+        self.count += other.count;
+    }
+}
+
+/// Serde wire form: the centroid list plus the total sample count.
+#[derive(Serialize, Deserialize)]
+struct DigestRepr {
+    centroids: Vec<(f64, f64)>,
+    count: usize,
+}
+
+impl Serialize for TDigestQuantileState {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        DigestRepr {
+            centroids: self.to_centroids(),
+            count: self.count,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TDigestQuantileState {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = DigestRepr::deserialize(deserializer)?;
+        Ok(Self::from_centroids(repr.centroids, repr.count))
+    }
+}
+
+impl QuantileEstimator for TDigestQuantileState {
+    fn insert(&mut self, x: f64) {
+        TDigestQuantileState::insert(self, x);
+    }
+
+    fn quantile(&self, q: f64) -> f64 {
+        TDigestQuantileState::quantile(self, q)
+    }
+
+    fn count(&self) -> usize {
+        self.count
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.merge_state(other);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recovers_tail_quantile() {
+        let mut state = TDigestQuantileState::new(10_000);
+        for i in 1..=10_000 {
+            state.insert(i as f64);
+        }
+        state.merge();
+        let p99 = state.quantile(0.99);
+        assert!((p99 - 9_900.0).abs() < 150.0, "p99 was {p99}");
+    }
+
+    #[test]
+    fn merge_state_matches_single() {
+        let mut whole = TDigestQuantileState::new(10_000);
+        let mut left = TDigestQuantileState::new(5_000);
+        let mut right = TDigestQuantileState::new(5_000);
+        for i in 1..=10_000 {
+            whole.insert(i as f64);
+            if i <= 5_000 {
+                left.insert(i as f64);
+            } else {
+                right.insert(i as f64);
+            }
+        }
+        left.merge_state(&right);
+        assert_eq!(left.count(), whole.count());
+        let (a, b) = (left.quantile(0.99), whole.quantile(0.99));
+        assert!((a - b).abs() < 150.0, "merged {a} vs whole {b}");
+    }
 }
@@ -0,0 +1,209 @@
+//! Biased-quantile streaming summary after Cormode, Korn, Muthukrishnan and
+//! Srivastava, "Effective Computation of Biased Quantiles over Data Streams".
+//!
+//! Unlike the t-digest backend this gives a *provable* relative-error bound at
+//! the extreme quantiles (p99/p999) that [`Statistics`](crate::stats::Statistics)
+//! reports, while keeping memory bounded by the chosen `epsilon`.
+
+use std::cmp::Ordering;
+
+use crate::estimator::QuantileEstimator;
+
+/// A single entry of the summary.
+///
+/// `g` is the gap — the difference in minimum rank from the previous entry —
+/// and `delta` is the uncertainty in `v`'s rank.
+#[derive(Debug, Clone)]
+struct Sample {
+    v: f64,
+    g: usize,
+    delta: usize,
+}
+
+#[derive(Debug)]
+pub struct CkmsQuantileState {
+    epsilon: f64,
+    samples: Vec<Sample>,
+    buffer: Vec<f64>,
+    buffer_cap: usize,
+    count: usize,
+}
+
+impl CkmsQuantileState {
+    pub fn new(epsilon: f64) -> Self {
+        // One compression per `1/(2*eps)` insertions keeps the amortised cost
+        // low while bounding the un-merged tail to a negligible fraction of `n`.
+        let buffer_cap = ((1.0 / (2.0 * epsilon)).ceil() as usize).max(1);
+        Self {
+            epsilon,
+            samples: Vec::new(),
+            buffer: Vec::with_capacity(buffer_cap),
+            buffer_cap,
+            count: 0,
+        }
+    }
+
+    pub fn insert(&mut self, x: f64) {
+        self.buffer.push(x);
+        if self.buffer.len() >= self.buffer_cap {
+            self.flush();
+        }
+    }
+
+    /// Merge the pending buffer into the summary and compress.
+    pub fn flush(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+        let mut pending = std::mem::replace(&mut self.buffer, Vec::with_capacity(self.buffer_cap));
+        pending.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        for x in pending {
+            self.insert_one(x);
+        }
+        self.compress();
+    }
+
+    /// The biased error function `f(r, n) = 2*eps*r`, which targets low
+    /// relative error at the extreme quantiles.
+    fn f(&self, r: f64) -> f64 {
+        2.0 * self.epsilon * r
+    }
+
+    fn insert_one(&mut self, x: f64) {
+        let idx = self
+            .samples
+            .iter()
+            .position(|s| s.v > x)
+            .unwrap_or(self.samples.len());
+
+        // A new minimum or maximum has no rank uncertainty; otherwise the new
+        // entry's uncertainty is `floor(2*eps*r)` at its minimum rank `r`.
+        let delta = if idx == 0 || idx == self.samples.len() {
+            0
+        } else {
+            let r: usize = self.samples[..idx].iter().map(|s| s.g).sum();
+            self.f(r as f64).floor() as usize
+        };
+
+        self.samples.insert(idx, Sample { v: x, g: 1, delta });
+        self.count += 1;
+    }
+
+    fn compress(&mut self) {
+        if self.samples.len() < 2 {
+            return;
+        }
+        let mut i = self.samples.len() - 2;
+        loop {
+            // `r_i` is the minimum rank of entry `i`: the running sum of gaps
+            // up to and including it.
+            let r_i: usize = self.samples[..=i].iter().map(|s| s.g).sum();
+            let threshold = self.f(r_i as f64).floor() as usize;
+            let merged = self.samples[i].g + self.samples[i + 1].g + self.samples[i + 1].delta;
+            if merged <= threshold {
+                self.samples[i + 1].g += self.samples[i].g;
+                self.samples.remove(i);
+            }
+            if i == 0 {
+                break;
+            }
+            i -= 1;
+        }
+    }
+
+    /// Fold the pending buffer into the summary read-only, so a `&self` query
+    /// sees every observed sample even before the next [`flush`](Self::flush).
+    /// Buffered samples are exact (`delta = 0`) additions of gap `1`.
+    fn merged_samples(&self) -> Vec<Sample> {
+        if self.buffer.is_empty() {
+            return self.samples.clone();
+        }
+        let mut combined = self.samples.clone();
+        for &x in &self.buffer {
+            let idx = combined
+                .iter()
+                .position(|s| s.v > x)
+                .unwrap_or(combined.len());
+            combined.insert(idx, Sample { v: x, g: 1, delta: 0 });
+        }
+        combined
+    }
+
+    pub fn quantile(&self, q: f64) -> f64 {
+        let samples = self.merged_samples();
+        if samples.is_empty() {
+            return f64::NAN;
+        }
+        let q = q.clamp(0.0, 1.0);
+        let rank = q * self.count() as f64;
+        let bound = self.f(rank) / 2.0;
+
+        let mut r_accum = 0usize;
+        let mut prev = samples[0].v;
+        for s in &samples {
+            if (r_accum + s.g + s.delta) as f64 > rank + bound {
+                return prev;
+            }
+            r_accum += s.g;
+            prev = s.v;
+        }
+        samples.last().unwrap().v
+    }
+
+    pub fn count(&self) -> usize {
+        self.count + self.buffer.len()
+    }
+}
+
+impl QuantileEstimator for CkmsQuantileState {
+    fn insert(&mut self, x: f64) {
+        CkmsQuantileState::insert(self, x);
+    }
+
+    fn quantile(&self, q: f64) -> f64 {
+        CkmsQuantileState::quantile(self, q)
+    }
+
+    fn count(&self) -> usize {
+        CkmsQuantileState::count(self)
+    }
+
+    /// The CKMS summary is not mergeable; rather than silently drop `other`'s
+    /// samples, reject any attempt to combine a non-empty state.
+    fn merge(&mut self, other: &Self) {
+        assert!(
+            other.count() == 0,
+            "CkmsQuantileState cannot be merged; combine raw streams instead"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recovers_quantiles_without_flush() {
+        // A stream shorter than `buffer_cap` never flushes; the query must
+        // still see the buffered samples rather than return NaN / count 0.
+        let mut state = CkmsQuantileState::new(0.001);
+        for i in 1..=100 {
+            state.insert(i as f64);
+        }
+        assert_eq!(state.count(), 100);
+        let p50 = state.quantile(0.5);
+        assert!((p50 - 50.0).abs() <= 1.0, "p50 was {p50}");
+    }
+
+    #[test]
+    fn tail_quantiles_within_epsilon() {
+        let eps = 0.001;
+        let mut state = CkmsQuantileState::new(eps);
+        for i in 1..=10_000 {
+            state.insert(i as f64);
+        }
+        assert_eq!(state.count(), 10_000);
+        let p99 = state.quantile(0.99);
+        assert!((p99 - 9_900.0).abs() <= eps * 10_000.0 + 1.0, "p99 was {p99}");
+    }
+}
@@ -0,0 +1,116 @@
+//! A quantile accumulator meant to be shared (`&self`, not `&mut self`)
+//! across worker threads, unlike every [`QuantileEstimator`](crate::estimator::QuantileEstimator)
+//! backend which assumes single-threaded ownership (or a caller-supplied
+//! `Mutex` around the whole state, which serializes every insert). Striping
+//! the buffer across shards means concurrent inserts from different threads
+//! usually land on different locks instead of fighting over one.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+
+use crate::quantile_brute::exact_quantile;
+use crate::stats::QuantileInterpolation;
+
+/// Sharded, lock-striped quantile accumulator. `quantile`/`count` merge every
+/// shard's buffer on demand rather than eagerly, so the cost of combining
+/// shards is only paid at report time, not per insert.
+#[derive(Debug)]
+pub struct ConcurrentQuantileState {
+    shards: Vec<Mutex<Vec<f64>>>,
+}
+
+impl ConcurrentQuantileState {
+    /// `shard_count` buffers, each behind its own lock; `0` is treated as `1`.
+    /// A reasonable choice is the expected number of concurrent writer
+    /// threads.
+    pub fn new(shard_count: usize) -> Self {
+        let shard_count = shard_count.max(1);
+        Self {
+            shards: (0..shard_count).map(|_| Mutex::new(Vec::new())).collect(),
+        }
+    }
+
+    /// Picks a shard from the calling thread's `ThreadId`, so a given thread
+    /// always strikes the same lock instead of round-robining and spreading
+    /// contention across every shard on every call.
+    fn shard_for_current_thread(&self) -> usize {
+        let mut hasher = DefaultHasher::new();
+        std::thread::current().id().hash(&mut hasher);
+        (hasher.finish() as usize) % self.shards.len()
+    }
+
+    /// Insert `x`, contending only with other threads mapped to the same
+    /// shard.
+    pub fn insert(&self, x: f64) {
+        let shard = self.shard_for_current_thread();
+        self.shards[shard].lock().unwrap().push(x);
+    }
+
+    pub fn count(&self) -> usize {
+        self.shards.iter().map(|s| s.lock().unwrap().len()).sum()
+    }
+
+    /// Merge every shard's buffer and compute the exact `q` quantile over the
+    /// union, matching a single-threaded [`BruteQuantileState`](crate::quantile_brute::BruteQuantileState)
+    /// fed the same values in any order. `O(n log n)` in the total sample
+    /// count — call this at report time, not per sample.
+    pub fn quantile(&self, q: f64) -> f64 {
+        let merged: Vec<f64> = self
+            .shards
+            .iter()
+            .flat_map(|s| s.lock().unwrap().clone())
+            .collect();
+        exact_quantile(&merged, q, QuantileInterpolation::Linear)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quantile_brute::BruteQuantileState;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn merged_quantile_matches_single_threaded_brute() {
+        let concurrent = Arc::new(ConcurrentQuantileState::new(8));
+        let data: Vec<f64> = (1..=20_000).map(|i| i as f64).collect();
+
+        let handles: Vec<_> = data
+            .chunks(2_000)
+            .map(|chunk| {
+                let state = Arc::clone(&concurrent);
+                let chunk = chunk.to_vec();
+                thread::spawn(move || {
+                    for x in chunk {
+                        state.insert(x);
+                    }
+                })
+            })
+            .collect();
+        for h in handles {
+            h.join().unwrap();
+        }
+
+        let mut brute = BruteQuantileState::new();
+        brute.extend(data.iter().copied());
+
+        assert_eq!(concurrent.count(), data.len());
+        for &q in &[0.0, 0.5, 0.9, 0.99, 1.0] {
+            let expected = brute.quantile(q, QuantileInterpolation::Linear);
+            let actual = concurrent.quantile(q);
+            assert!(
+                (expected - actual).abs() < 1e-9,
+                "q={q} expected {expected}, got {actual}"
+            );
+        }
+    }
+
+    #[test]
+    fn empty_state_reports_nan() {
+        let state = ConcurrentQuantileState::new(4);
+        assert_eq!(state.count(), 0);
+        assert!(state.quantile(0.5).is_nan());
+    }
+}
@@ -1,6 +1,42 @@
 use std::cmp::Ordering;
 use std::collections::HashMap;
 
+/// How a quantile is resolved when the fractional rank `h = (n-1)*q` falls
+/// between the two bracketing samples `data[lo]` and `data[hi]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuantileInterpolation {
+    /// Interpolate linearly between the bracketing samples (the default).
+    Linear,
+    /// Take the lower bracketing sample, `data[lo]`.
+    Lower,
+    /// Take the higher bracketing sample, `data[hi]`.
+    Higher,
+    /// Take whichever bracketing sample is closer, breaking ties towards `lo`.
+    Nearest,
+    /// Take the unweighted mean of the two bracketing samples.
+    Midpoint,
+}
+
+impl QuantileInterpolation {
+    /// Combine the two bracketing samples given the fractional weight
+    /// `w = h - lo` of the higher sample.
+    pub(crate) fn apply(self, lo_val: f64, hi_val: f64, w: f64) -> f64 {
+        match self {
+            QuantileInterpolation::Linear => lo_val + (hi_val - lo_val) * w,
+            QuantileInterpolation::Lower => lo_val,
+            QuantileInterpolation::Higher => hi_val,
+            QuantileInterpolation::Nearest => {
+                if w > 0.5 {
+                    hi_val
+                } else {
+                    lo_val
+                }
+            }
+            QuantileInterpolation::Midpoint => (lo_val + hi_val) / 2.0,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Statistics {
     pub avg: f64,
@@ -17,6 +53,13 @@ pub struct Statistics {
 }
 
 pub fn statistics_from_sorted(data: &[f64]) -> Statistics {
+    statistics_from_sorted_with_interp(data, QuantileInterpolation::Linear)
+}
+
+pub fn statistics_from_sorted_with_interp(
+    data: &[f64],
+    interp: QuantileInterpolation,
+) -> Statistics {
     if data.is_empty() {
         return Statistics {
             avg: f64::NAN,
@@ -48,7 +91,7 @@ pub fn statistics_from_sorted(data: &[f64]) -> Statistics {
             return data[lo];
         }
         let w = h - (lo as f64);
-        data[lo] + (data[hi] - data[lo]) * w
+        interp.apply(data[lo], data[hi], w)
     };
 
     Statistics {
@@ -66,9 +109,16 @@ pub fn statistics_from_sorted(data: &[f64]) -> Statistics {
     }
 }
 
-pub fn statistics_from_vec(mut data: Vec<f64>) -> Statistics {
+pub fn statistics_from_vec(data: Vec<f64>) -> Statistics {
+    statistics_from_vec_with_interp(data, QuantileInterpolation::Linear)
+}
+
+pub fn statistics_from_vec_with_interp(
+    mut data: Vec<f64>,
+    interp: QuantileInterpolation,
+) -> Statistics {
     data.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
-    statistics_from_sorted(&data)
+    statistics_from_sorted_with_interp(&data, interp)
 }
 
 pub fn f64_from_stat(map: &HashMap<String, serde_json::Value>, key: &str) -> Option<f64> {
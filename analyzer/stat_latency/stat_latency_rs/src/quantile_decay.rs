@@ -0,0 +1,154 @@
+//! Time-decayed quantiles, for a soak test that wants "p99 over roughly the
+//! last hour" rather than "p99 over the whole run" — a plain
+//! [`TDigestQuantileState`] weights a sample from the first minute the same
+//! as one from the last, so a permanent regime shift only shows up once
+//! enough new samples have diluted the old ones. This scales the digest's
+//! existing centroid weights down instead, so old samples fade out on a
+//! configurable half-life rather than a sample count.
+
+use crate::quantile_tdigest::{TDigestConfig, TDigestQuantileState};
+
+/// A [`TDigestQuantileState`] whose centroid weights are periodically
+/// scaled down, so `quantile` tracks recent traffic and forgets old
+/// behavior within `half_life` of it.
+#[derive(Debug)]
+pub struct DecayingQuantileState {
+    inner: TDigestQuantileState,
+    /// Sample count at last effective weight, decayed alongside the
+    /// centroids so [`len`](Self::len) stays consistent with what
+    /// `quantile` is actually weighting.
+    effective_count: f64,
+    half_life: f64,
+}
+
+impl DecayingQuantileState {
+    /// `half_life` is in whatever unit the caller passes to
+    /// [`decay_for_elapsed`](Self::decay_for_elapsed) (seconds, inserts,
+    /// whatever "time" means for the caller); after one half-life's worth of
+    /// elapsed decay, existing samples count for half their original weight.
+    /// Panics if `half_life` is not a positive, finite number.
+    pub fn with_half_life(half_life: f64) -> Self {
+        assert!(half_life > 0.0 && half_life.is_finite(), "half_life must be positive and finite");
+        Self {
+            inner: TDigestQuantileState::new(0),
+            effective_count: 0.0,
+            half_life,
+        }
+    }
+
+    /// Like [`with_half_life`](Self::with_half_life) but with an explicit
+    /// [`TDigestConfig`] for the underlying digest.
+    pub fn with_half_life_and_config(half_life: f64, config: TDigestConfig) -> Self {
+        assert!(half_life > 0.0 && half_life.is_finite(), "half_life must be positive and finite");
+        Self {
+            inner: TDigestQuantileState::with_config(config),
+            effective_count: 0.0,
+            half_life,
+        }
+    }
+
+    pub fn insert(&mut self, x: f64) {
+        self.inner.insert(x);
+        self.effective_count += 1.0;
+    }
+
+    pub fn quantile(&self, q: f64) -> f64 {
+        self.inner.quantile(q)
+    }
+
+    /// Scale every existing centroid's weight (and the running effective
+    /// count) by `factor`. `factor` is typically in `[0.0, 1.0]` — `1.0` is
+    /// a no-op, `0.0` forgets everything inserted so far — but isn't
+    /// clamped, since a caller composing several decay steps may want to
+    /// pass a factor `> 1.0` to undo one. Flushes any buffered samples
+    /// first, since [`TDigestQuantileState::centroids`] only sees what's
+    /// already in the digest.
+    pub fn decay(&mut self, factor: f64) {
+        self.inner.flush();
+        let scaled: Vec<(f64, f64)> = self
+            .inner
+            .centroids()
+            .into_iter()
+            .map(|(mean, weight)| (mean, weight * factor))
+            .collect();
+        self.effective_count *= factor;
+        self.inner = TDigestQuantileState::from_centroids(scaled, self.effective_count.round() as usize);
+    }
+
+    /// Decay by the factor `0.5.powf(elapsed / half_life)` implied by
+    /// `elapsed` time passing at this state's configured half-life — the
+    /// natural entry point for "5 seconds have passed since the last flush,
+    /// age the digest accordingly" callers.
+    pub fn decay_for_elapsed(&mut self, elapsed: f64) {
+        self.decay(0.5_f64.powf(elapsed / self.half_life));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.effective_count == 0.0
+    }
+
+    /// The effective (decayed) sample count backing the digest, rounded to
+    /// the nearest whole sample. Not the same as the number of `insert`
+    /// calls ever made, once any decay has happened.
+    pub fn len(&self) -> usize {
+        self.effective_count.round() as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decay_by_half_halves_reported_weight() {
+        let mut state = DecayingQuantileState::with_half_life(100.0);
+        for i in 1..=1000 {
+            state.insert(i as f64);
+        }
+        let before = state.len();
+        state.decay(0.5);
+        assert!((state.len() as f64 - before as f64 / 2.0).abs() <= 1.0);
+    }
+
+    #[test]
+    fn decay_for_elapsed_one_half_life_matches_decay_by_half() {
+        let mut a = DecayingQuantileState::with_half_life(60.0);
+        let mut b = DecayingQuantileState::with_half_life(60.0);
+        for i in 1..=500 {
+            a.insert(i as f64);
+            b.insert(i as f64);
+        }
+        a.decay(0.5);
+        b.decay_for_elapsed(60.0);
+        assert_eq!(a.len(), b.len());
+        assert!((a.quantile(0.5) - b.quantile(0.5)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn sustained_regime_shift_converges_after_enough_decay() {
+        let mut state = DecayingQuantileState::with_half_life(1000.0);
+        for _ in 0..2000 {
+            state.insert(100.0);
+        }
+        // Age out the old regime, then feed a sustained shift to a much
+        // higher latency.
+        for _ in 0..5 {
+            state.decay_for_elapsed(1000.0);
+        }
+        for _ in 0..2000 {
+            state.insert(1_000.0);
+        }
+        assert!(state.quantile(0.99) > 500.0, "p99 was {}", state.quantile(0.99));
+    }
+
+    #[test]
+    fn decay_of_zero_forgets_everything() {
+        let mut state = DecayingQuantileState::with_half_life(10.0);
+        for i in 1..=100 {
+            state.insert(i as f64);
+        }
+        state.decay(0.0);
+        assert!(state.is_empty());
+        assert_eq!(state.len(), 0);
+    }
+}
@@ -0,0 +1,198 @@
+//! Greenwald-Khanna streaming quantile summary ("Space-Efficient Online
+//! Computation of Quantile Summaries"), the uniform-error counterpart to
+//! [`CkmsQuantileState`](crate::quantile_ckms::CkmsQuantileState)'s biased
+//! summary: every quantile, not just the tails, is bounded within `epsilon`
+//! of its true rank, in `O(1/epsilon * log(epsilon * n))` space.
+
+use std::cmp::Ordering;
+
+use crate::estimator::QuantileEstimator;
+
+/// A single entry of the summary. `g` is the gap in minimum rank from the
+/// previous entry and `delta` is the uncertainty in `v`'s rank, same roles as
+/// in the CKMS summary but with the uniform (not biased) error function.
+#[derive(Debug, Clone)]
+struct Sample {
+    v: f64,
+    g: usize,
+    delta: usize,
+}
+
+#[derive(Debug)]
+pub struct GkQuantileState {
+    epsilon: f64,
+    samples: Vec<Sample>,
+    count: usize,
+}
+
+impl GkQuantileState {
+    /// `epsilon` bounds the absolute rank error: every returned quantile's
+    /// true rank is within `epsilon * count()` of the requested rank.
+    pub fn new(epsilon: f64) -> Self {
+        assert!(epsilon > 0.0 && epsilon < 1.0, "epsilon must be in (0, 1)");
+        Self {
+            epsilon,
+            samples: Vec::new(),
+            count: 0,
+        }
+    }
+
+    pub fn insert(&mut self, x: f64) {
+        let idx = self
+            .samples
+            .iter()
+            .position(|s| s.v > x)
+            .unwrap_or(self.samples.len());
+
+        // A new minimum or maximum has no rank uncertainty; otherwise the
+        // uncertainty budget is `floor(2*eps*n) - 1` at insertion time.
+        let delta = if idx == 0 || idx == self.samples.len() {
+            0
+        } else {
+            ((2.0 * self.epsilon * self.count as f64).floor() as usize).saturating_sub(1)
+        };
+
+        self.samples.insert(idx, Sample { v: x, g: 1, delta });
+        self.count += 1;
+
+        // Compress roughly every `1/(2*eps)` insertions, matching the
+        // amortised schedule in Greenwald-Khanna's original presentation.
+        let period = ((1.0 / (2.0 * self.epsilon)).ceil() as usize).max(1);
+        if self.count % period == 0 {
+            self.compress();
+        }
+    }
+
+    fn compress(&mut self) {
+        if self.samples.len() < 2 {
+            return;
+        }
+        let band = (2.0 * self.epsilon * self.count as f64).floor() as usize;
+        let mut i = self.samples.len() - 2;
+        loop {
+            let merged = self.samples[i].g + self.samples[i + 1].g + self.samples[i + 1].delta;
+            if merged <= band {
+                self.samples[i + 1].g += self.samples[i].g;
+                self.samples.remove(i);
+            }
+            if i == 0 {
+                break;
+            }
+            i -= 1;
+        }
+    }
+
+    pub fn quantile(&self, q: f64) -> f64 {
+        if self.samples.is_empty() {
+            return f64::NAN;
+        }
+        let q = q.clamp(0.0, 1.0);
+        let rank = q * self.count as f64;
+        let band = self.epsilon * self.count as f64;
+
+        let mut r_accum = 0usize;
+        let mut prev = self.samples[0].v;
+        for s in &self.samples {
+            if (r_accum + s.g + s.delta) as f64 > rank + band {
+                return prev;
+            }
+            r_accum += s.g;
+            prev = s.v;
+        }
+        self.samples.last().unwrap().v
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl QuantileEstimator for GkQuantileState {
+    fn insert(&mut self, x: f64) {
+        GkQuantileState::insert(self, x);
+    }
+
+    fn quantile(&self, q: f64) -> f64 {
+        GkQuantileState::quantile(self, q)
+    }
+
+    fn count(&self) -> usize {
+        self.count
+    }
+
+    /// The GK summary is not mergeable; reject any attempt to combine a
+    /// non-empty state rather than silently dropping its samples.
+    fn merge(&mut self, other: &Self) {
+        assert!(
+            other.count() == 0,
+            "GkQuantileState cannot be merged; combine raw streams instead"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quantile_brute::exact_quantile;
+    use crate::stats::QuantileInterpolation;
+
+    #[test]
+    fn recovers_quantiles_on_small_input() {
+        let mut state = GkQuantileState::new(0.01);
+        for i in 1..=100 {
+            state.insert(i as f64);
+        }
+        assert_eq!(state.count(), 100);
+        let p50 = state.quantile(0.5);
+        assert!((p50 - 50.0).abs() <= 2.0, "p50 was {p50}");
+    }
+
+    #[test]
+    fn error_stays_within_epsilon_on_adversarial_sorted_input() {
+        let eps = 0.01;
+        let mut state = GkQuantileState::new(eps);
+        let mut sorted_asc = false;
+        // Insert in sorted order: an adversarial input order for summaries
+        // that rely on compression triggering evenly across the value range.
+        for i in 1..=10_000 {
+            state.insert(i as f64);
+            sorted_asc = sorted_asc || i > 1;
+        }
+        assert!(sorted_asc);
+
+        let data: Vec<f64> = (1..=10_000).map(|i| i as f64).collect();
+        for &q in &[0.1, 0.5, 0.9, 0.99] {
+            let approx = state.quantile(q);
+            let exact = exact_quantile(&data, q, QuantileInterpolation::Linear);
+            let allowed_error = eps * state.count() as f64 + 1.0;
+            assert!(
+                (approx - exact).abs() <= allowed_error,
+                "q={q} approx={approx} exact={exact} allowed={allowed_error}"
+            );
+        }
+    }
+
+    #[test]
+    fn error_stays_within_epsilon_on_reverse_sorted_input() {
+        let eps = 0.02;
+        let mut state = GkQuantileState::new(eps);
+        for i in (1..=5_000).rev() {
+            state.insert(i as f64);
+        }
+        let data: Vec<f64> = (1..=5_000).map(|i| i as f64).collect();
+        let approx = state.quantile(0.95);
+        let exact = exact_quantile(&data, 0.95, QuantileInterpolation::Linear);
+        let allowed_error = eps * state.count() as f64 + 1.0;
+        assert!((approx - exact).abs() <= allowed_error, "approx={approx} exact={exact}");
+    }
+
+    #[test]
+    #[should_panic]
+    fn merge_rejects_non_empty_other() {
+        let mut a = GkQuantileState::new(0.01);
+        a.insert(1.0);
+        let mut b = GkQuantileState::new(0.01);
+        b.insert(2.0);
+        a.merge(&b);
+    }
+}
@@ -0,0 +1,133 @@
+use hdrhistogram::Histogram;
+
+use crate::estimator::QuantileEstimator;
+
+/// HdrHistogram-backed quantile state, for callers whose samples are already
+/// integer nanosecond/microsecond latencies and want the format's bounded
+/// relative-error guarantee (set by `sig_figs`) in fixed memory, rather than
+/// the data-dependent error of a t-digest or streaming summary.
+#[derive(Debug)]
+pub struct HdrQuantileState {
+    histogram: Histogram<u64>,
+    lowest: u64,
+    highest: u64,
+    clamped_count: usize,
+}
+
+impl HdrQuantileState {
+    /// `lowest`/`highest` bound the trackable range (inclusive) and
+    /// `sig_figs` (0-5) is the number of significant decimal digits preserved
+    /// at every magnitude. Panics if the bounds or `sig_figs` are invalid, per
+    /// [`Histogram::new_with_bounds`].
+    pub fn new(lowest: u64, highest: u64, sig_figs: u8) -> Self {
+        let histogram = Histogram::new_with_bounds(lowest.max(1), highest, sig_figs)
+            .expect("invalid HdrHistogram bounds/significant-figures");
+        Self {
+            histogram,
+            lowest,
+            highest,
+            clamped_count: 0,
+        }
+    }
+
+    /// Round `x` to the nearest integer and record it, saturating to
+    /// `[lowest, highest]` and bumping [`clamped_count`](Self::clamped_count)
+    /// when `x` falls outside that range rather than dropping the sample.
+    pub fn insert(&mut self, x: f64) {
+        let rounded = x.round();
+        let value = if rounded <= self.lowest as f64 {
+            if rounded < self.lowest as f64 {
+                self.clamped_count += 1;
+            }
+            self.lowest
+        } else if rounded >= self.highest as f64 {
+            if rounded > self.highest as f64 {
+                self.clamped_count += 1;
+            }
+            self.highest
+        } else {
+            rounded as u64
+        };
+        self.histogram
+            .record(value)
+            .expect("value clamped to [lowest, highest] must be recordable");
+    }
+
+    pub fn quantile(&self, q: f64) -> f64 {
+        if self.histogram.is_empty() {
+            return f64::NAN;
+        }
+        self.histogram.value_at_quantile(q.clamp(0.0, 1.0)) as f64
+    }
+
+    pub fn count(&self) -> usize {
+        self.histogram.len() as usize
+    }
+
+    /// Number of inserted samples that fell outside `[lowest, highest]` and
+    /// were saturated to a bound instead of recorded exactly.
+    pub fn clamped_count(&self) -> usize {
+        self.clamped_count
+    }
+}
+
+impl QuantileEstimator for HdrQuantileState {
+    fn insert(&mut self, x: f64) {
+        HdrQuantileState::insert(self, x);
+    }
+
+    fn quantile(&self, q: f64) -> f64 {
+        HdrQuantileState::quantile(self, q)
+    }
+
+    fn count(&self) -> usize {
+        HdrQuantileState::count(self)
+    }
+
+    /// Two `Histogram`s can only be added when their bucket configurations
+    /// agree; rather than risk a mismatched-config panic deep in the
+    /// `hdrhistogram` crate, reject any attempt to combine a non-empty state,
+    /// matching the non-mergeable backends (`Ckms`, `Reservoir`).
+    fn merge(&mut self, other: &Self) {
+        assert!(
+            other.count() == 0,
+            "HdrQuantileState cannot be merged; combine raw streams instead"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recovers_quantiles_within_sig_figs() {
+        let mut state = HdrQuantileState::new(1, 100_000, 3);
+        for i in 1..=10_000u64 {
+            state.insert(i as f64);
+        }
+        assert_eq!(state.count(), 10_000);
+        let p99 = state.quantile(0.99);
+        assert!((p99 - 9_900.0).abs() < 20.0, "p99 was {p99}");
+        assert_eq!(state.clamped_count(), 0);
+    }
+
+    #[test]
+    fn out_of_range_samples_saturate_and_are_counted() {
+        let mut state = HdrQuantileState::new(10, 1_000, 3);
+        state.insert(0.0);
+        state.insert(5_000.0);
+        state.insert(500.0);
+        assert_eq!(state.count(), 3);
+        assert_eq!(state.clamped_count(), 2);
+        assert_eq!(state.quantile(1.0), 1_000.0);
+        assert_eq!(state.quantile(0.0), 10.0);
+    }
+
+    #[test]
+    fn empty_state_reports_nan() {
+        let state = HdrQuantileState::new(1, 1_000, 3);
+        assert_eq!(state.count(), 0);
+        assert!(state.quantile(0.5).is_nan());
+    }
+}
@@ -0,0 +1,215 @@
+//! Fixed-bucket histogram quantile estimator with caller-supplied bucket
+//! edges.
+//!
+//! Unlike every other backend in this crate, accuracy here isn't a knob
+//! (`epsilon`, `compression`) tuned against an error bound — it's simply the
+//! width of whichever buckets the caller picked, so a caller who knows their
+//! data's shape in advance (e.g. "everything is between 1ms and 10s") can pin
+//! down exactly how much memory and CPU an insert costs. `insert` is an
+//! `O(log buckets)` binary search plus one counter increment: no sorting, no
+//! compression pass, no allocation — the cheapest estimator this crate has,
+//! at the cost of the caller choosing good edges up front.
+
+use crate::estimator::QuantileEstimator;
+
+/// A histogram over caller-supplied bucket boundaries.
+///
+/// `edges` are `N + 1` strictly increasing boundaries defining `N` buckets:
+/// bucket `i` covers `[edges[i], edges[i + 1])`. A sample below `edges[0]` or
+/// at/above `edges[N]` is clamped into the first/last bucket rather than
+/// dropped, so `count()` always reflects every inserted sample even if it
+/// fell outside the configured range. [`quantile`](Self::quantile) linearly
+/// interpolates within the target bucket assuming a uniform distribution of
+/// samples across it — accuracy is bounded by that bucket's width, which the
+/// caller controls, not by anything this type can improve on internally.
+#[derive(Debug, Clone)]
+pub struct HistogramQuantileState {
+    edges: Vec<f64>,
+    counts: Vec<u64>,
+    count: usize,
+}
+
+impl HistogramQuantileState {
+    /// Panics if `edges` has fewer than two boundaries, isn't strictly
+    /// increasing, or contains a NaN — a caller-supplied bucket layout that
+    /// fails any of these can't answer a well-defined quantile, so it's
+    /// rejected up front rather than silently misbehaving on the first
+    /// insert.
+    pub fn new(edges: Vec<f64>) -> Self {
+        assert!(edges.len() >= 2, "HistogramQuantileState needs at least 2 edges to form a bucket");
+        assert!(
+            edges.windows(2).all(|w| w[0] < w[1]),
+            "HistogramQuantileState edges must be strictly increasing, got {edges:?}"
+        );
+        let counts = vec![0u64; edges.len() - 1];
+        Self { edges, counts, count: 0 }
+    }
+
+    /// The bucket index `x` falls into, clamped into range for anything
+    /// outside `[edges[0], edges[last])`. `O(log buckets)` via binary search
+    /// over the sorted edges.
+    fn bucket_of(&self, x: f64) -> usize {
+        let idx = self.edges.partition_point(|&e| e <= x);
+        idx.saturating_sub(1).min(self.counts.len() - 1)
+    }
+
+    pub fn insert(&mut self, x: f64) {
+        let bucket = self.bucket_of(x);
+        self.counts[bucket] += 1;
+        self.count += 1;
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Linearly interpolate the value at rank `q * count()` within whichever
+    /// bucket that rank falls in, treating samples as uniformly spread across
+    /// the bucket's width. `NaN` on an empty state.
+    pub fn quantile(&self, q: f64) -> f64 {
+        if self.count == 0 {
+            return f64::NAN;
+        }
+        let q = q.clamp(0.0, 1.0);
+        let target = q * self.count as f64;
+
+        let mut cumulative = 0u64;
+        for (i, &c) in self.counts.iter().enumerate() {
+            let next_cumulative = cumulative + c;
+            if (next_cumulative as f64) >= target || i == self.counts.len() - 1 {
+                if c == 0 {
+                    return self.edges[i];
+                }
+                let within = ((target - cumulative as f64) / (c as f64)).clamp(0.0, 1.0);
+                let lo = self.edges[i];
+                let hi = self.edges[i + 1];
+                return lo + (hi - lo) * within;
+            }
+            cumulative = next_cumulative;
+        }
+        *self.edges.last().unwrap()
+    }
+
+    /// Fold `other`'s bucket counts into `self`. Unlike most of this crate's
+    /// approximate backends, a fixed-bucket histogram merges exactly (no
+    /// accuracy loss) as long as both states share the same `edges` — panics
+    /// otherwise, since summing counts across mismatched bucket boundaries
+    /// would silently produce a meaningless histogram.
+    pub fn merge(&mut self, other: &Self) {
+        assert_eq!(
+            self.edges, other.edges,
+            "HistogramQuantileState can only merge states built with identical edges"
+        );
+        for (a, b) in self.counts.iter_mut().zip(&other.counts) {
+            *a += b;
+        }
+        self.count += other.count;
+    }
+}
+
+impl QuantileEstimator for HistogramQuantileState {
+    fn insert(&mut self, x: f64) {
+        HistogramQuantileState::insert(self, x);
+    }
+
+    fn quantile(&self, q: f64) -> f64 {
+        HistogramQuantileState::quantile(self, q)
+    }
+
+    fn count(&self) -> usize {
+        self.count
+    }
+
+    fn merge(&mut self, other: &Self) {
+        HistogramQuantileState::merge(self, other);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantile_interpolates_within_the_target_bucket() {
+        let mut state = HistogramQuantileState::new(vec![0.0, 10.0, 20.0, 30.0]);
+        for i in 0..30 {
+            state.insert(i as f64);
+        }
+        assert_eq!(state.count(), 30);
+        // p50 lands squarely in the middle bucket [10, 20).
+        let p50 = state.quantile(0.5);
+        assert!((10.0..20.0).contains(&p50), "p50 was {p50}");
+    }
+
+    #[test]
+    fn quantile_is_exact_on_a_single_bucket_boundary_at_the_extremes() {
+        let mut state = HistogramQuantileState::new(vec![0.0, 100.0]);
+        for _ in 0..100 {
+            state.insert(50.0);
+        }
+        assert_eq!(state.quantile(0.0), 0.0);
+    }
+
+    #[test]
+    fn out_of_range_samples_clamp_into_the_extreme_buckets() {
+        let mut state = HistogramQuantileState::new(vec![0.0, 10.0, 20.0]);
+        state.insert(-1000.0);
+        state.insert(1000.0);
+        assert_eq!(state.count(), 2);
+    }
+
+    #[test]
+    fn quantile_is_nan_on_an_empty_state() {
+        let state = HistogramQuantileState::new(vec![0.0, 1.0]);
+        assert!(state.quantile(0.5).is_nan());
+    }
+
+    #[test]
+    #[should_panic(expected = "strictly increasing")]
+    fn new_rejects_non_monotonic_edges() {
+        HistogramQuantileState::new(vec![0.0, 10.0, 5.0, 20.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least 2 edges")]
+    fn new_rejects_too_few_edges() {
+        HistogramQuantileState::new(vec![0.0]);
+    }
+
+    #[test]
+    fn merge_combines_counts_across_matching_edges() {
+        let edges = vec![0.0, 10.0, 20.0, 30.0];
+        let mut left = HistogramQuantileState::new(edges.clone());
+        let mut right = HistogramQuantileState::new(edges);
+        for i in 0..15 {
+            left.insert(i as f64);
+        }
+        for i in 15..30 {
+            right.insert(i as f64);
+        }
+        left.merge(&right);
+        assert_eq!(left.count(), 30);
+        let p99 = left.quantile(0.99);
+        assert!((p99 - 30.0).abs() < 1.0, "p99 was {p99}");
+    }
+
+    #[test]
+    #[should_panic(expected = "identical edges")]
+    fn merge_rejects_mismatched_edges() {
+        let mut left = HistogramQuantileState::new(vec![0.0, 10.0, 20.0]);
+        let right = HistogramQuantileState::new(vec![0.0, 5.0, 20.0]);
+        left.merge(&right);
+    }
+
+    #[test]
+    fn accuracy_stays_within_one_bucket_width_of_the_true_value() {
+        let bucket_width = 100.0;
+        let edges: Vec<f64> = (0..=100).map(|i| i as f64 * bucket_width).collect();
+        let mut state = HistogramQuantileState::new(edges);
+        for i in 1..=9_999 {
+            state.insert(i as f64);
+        }
+        let p50 = state.quantile(0.5);
+        assert!((p50 - 5_000.0).abs() < bucket_width, "p50 was {p50}");
+    }
+}
@@ -0,0 +1,163 @@
+//! Exact quantiles over `u64` nanosecond timestamps, for golden-file tests
+//! where [`BruteQuantileState`](crate::quantile_brute::BruteQuantileState)'s
+//! `f64` storage would introduce nondeterminism: sums and comparisons above
+//! `2^53` ns (~104 days) lose precision in `f64`, and even below that bound a
+//! platform's rounding mode can nudge a quantile by an ULP between runs.
+//! Staying in integer space the whole way through sidesteps both.
+
+use std::cell::RefCell;
+
+/// Exact quantile state over `u64` nanosecond durations, kept as a sorted
+/// `Vec` the same way [`BruteQuantileState`](crate::quantile_brute::BruteQuantileState)
+/// is; there is no approximate/mergeable variant of this state because its
+/// entire reason to exist is exactness.
+#[derive(Debug, Default)]
+pub struct IntQuantileState {
+    values: Vec<u64>,
+    /// Lazily-rebuilt sorted copy of `values`, invalidated by every mutation,
+    /// mirroring [`BruteQuantileState`](crate::quantile_brute::BruteQuantileState)'s
+    /// sort cache.
+    sorted: RefCell<Option<Vec<u64>>>,
+}
+
+impl IntQuantileState {
+    pub fn new() -> Self {
+        Self {
+            values: Vec::new(),
+            sorted: RefCell::new(None),
+        }
+    }
+
+    pub fn insert(&mut self, ns: u64) {
+        self.values.push(ns);
+        *self.sorted.get_mut() = None;
+    }
+
+    pub fn extend(&mut self, values: impl IntoIterator<Item = u64>) {
+        self.values.extend(values);
+        *self.sorted.get_mut() = None;
+    }
+
+    fn ensure_sorted(&self) {
+        if self.sorted.borrow().is_none() {
+            let mut sorted = self.values.clone();
+            sorted.sort_unstable();
+            *self.sorted.borrow_mut() = Some(sorted);
+        }
+    }
+
+    /// The `q`-th quantile via nearest-rank selection: `rank =
+    /// ceil(q * n)` clamped to `[1, n]`, returning `values[rank - 1]` of the
+    /// sorted data. Unlike [`BruteQuantileState`](crate::quantile_brute::BruteQuantileState)'s
+    /// linear interpolation, this never averages two samples, so the result
+    /// is always one of the inserted `u64` values with no rounding at all.
+    /// Returns `0` on an empty state; callers who need to distinguish "no
+    /// data" from a genuine zero-duration sample should check
+    /// [`is_empty`](Self::is_empty) first.
+    pub fn quantile(&self, q: f64) -> u64 {
+        if self.values.is_empty() {
+            return 0;
+        }
+        self.ensure_sorted();
+        let sorted = self.sorted.borrow();
+        let sorted = sorted.as_ref().unwrap();
+        let q = q.clamp(0.0, 1.0);
+        let rank = (q * sorted.len() as f64).ceil() as usize;
+        let rank = rank.clamp(1, sorted.len());
+        sorted[rank - 1]
+    }
+
+    pub fn count(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.values.clear();
+        *self.sorted.get_mut() = None;
+    }
+
+    /// A sorted copy of the raw samples, mirroring
+    /// [`BruteQuantileState::sorted_values`](crate::quantile_brute::BruteQuantileState::sorted_values).
+    /// There is no NaN to worry about in integer space, so no custom
+    /// comparator is needed.
+    pub fn sorted_values(&self) -> Vec<u64> {
+        self.ensure_sorted();
+        self.sorted.borrow().as_ref().unwrap().clone()
+    }
+}
+
+impl Extend<u64> for IntQuantileState {
+    fn extend<I: IntoIterator<Item = u64>>(&mut self, iter: I) {
+        IntQuantileState::extend(self, iter);
+    }
+}
+
+impl FromIterator<u64> for IntQuantileState {
+    fn from_iter<I: IntoIterator<Item = u64>>(iter: I) -> Self {
+        let mut state = IntQuantileState::new();
+        state.extend(iter);
+        state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantile_matches_nearest_rank_on_known_data() {
+        let mut state = IntQuantileState::new();
+        for i in 1..=10u64 {
+            state.insert(i);
+        }
+        assert_eq!(state.quantile(0.0), 1);
+        assert_eq!(state.quantile(0.5), 5);
+        assert_eq!(state.quantile(1.0), 10);
+    }
+
+    #[test]
+    fn quantile_is_zero_on_empty_state() {
+        let state = IntQuantileState::new();
+        assert_eq!(state.quantile(0.5), 0);
+        assert!(state.is_empty());
+    }
+
+    #[test]
+    fn quantile_stays_exact_above_f64_mantissa_precision() {
+        let base: u64 = 1 << 60;
+        let mut state = IntQuantileState::new();
+        for i in 0..5u64 {
+            state.insert(base + i);
+        }
+        assert_eq!(state.quantile(1.0), base + 4);
+    }
+
+    #[test]
+    fn extend_and_from_iterator_agree_with_repeated_insert() {
+        let mut one_by_one = IntQuantileState::new();
+        for i in 1..=100u64 {
+            one_by_one.insert(i);
+        }
+        let collected: IntQuantileState = (1..=100u64).collect();
+        assert_eq!(one_by_one.quantile(0.9), collected.quantile(0.9));
+        assert_eq!(one_by_one.count(), collected.count());
+    }
+
+    #[test]
+    fn sorted_values_returns_ascending_copy() {
+        let mut state = IntQuantileState::new();
+        for &x in &[5u64, 1, 4, 2, 3] {
+            state.insert(x);
+        }
+        assert_eq!(state.sorted_values(), vec![1, 2, 3, 4, 5]);
+        assert_eq!(state.len(), 5);
+    }
+}
@@ -0,0 +1,255 @@
+//! The P² algorithm (Jain & Chlamtac, "The P2 Algorithm for Dynamic
+//! Calculation of Quantiles and Histograms Without Storing Observations"):
+//! a single target quantile tracked in five `f64`s and no retained samples at
+//! all, unlike every other backend in this module which keeps at least a
+//! compressed summary.
+
+use crate::estimator::QuantileEstimator;
+
+/// Tracks one target quantile `q` in constant memory: five marker heights and
+/// their (possibly fractional) positions, adjusted after every insert via the
+/// parabolic (falling back to linear) interpolation formula.
+#[derive(Debug, Clone)]
+pub struct PSquareQuantileState {
+    q: f64,
+    /// Marker heights `q_1..q_5`: min, the three quantile-straddling markers,
+    /// and max.
+    heights: [f64; 5],
+    /// Current (possibly non-integer, post-adjustment) marker positions.
+    positions: [f64; 5],
+    /// Desired marker positions, updated after every insert.
+    desired: [f64; 5],
+    /// Count of samples seen so far; the first 5 initialise the markers
+    /// directly rather than going through the P² update rule.
+    count: usize,
+    init: Vec<f64>,
+}
+
+impl PSquareQuantileState {
+    /// `q` is the single quantile this state will answer, e.g. `0.99` for a
+    /// dedicated p99 tracker.
+    pub fn new(q: f64) -> Self {
+        assert!((0.0..=1.0).contains(&q), "q must be in [0, 1]");
+        Self {
+            q,
+            heights: [0.0; 5],
+            positions: [1.0, 2.0, 3.0, 4.0, 5.0],
+            desired: [1.0, 2.0, 3.0, 4.0, 5.0],
+            count: 0,
+            init: Vec::with_capacity(5),
+        }
+    }
+
+    pub fn insert(&mut self, x: f64) {
+        self.count += 1;
+        if self.init.len() < 5 {
+            self.init.push(x);
+            if self.init.len() == 5 {
+                self.init.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                self.heights = [
+                    self.init[0],
+                    self.init[1],
+                    self.init[2],
+                    self.init[3],
+                    self.init[4],
+                ];
+            }
+            return;
+        }
+
+        // Find the cell `k` that `x` falls into and bump every position to
+        // its right, then grow the range markers if `x` is a new extreme.
+        let k = if x < self.heights[0] {
+            self.heights[0] = x;
+            0
+        } else if x >= self.heights[4] {
+            self.heights[4] = x;
+            3
+        } else {
+            let mut k = 0;
+            for i in 0..4 {
+                if self.heights[i] <= x && x < self.heights[i + 1] {
+                    k = i;
+                    break;
+                }
+            }
+            k
+        };
+        for pos in self.positions.iter_mut().skip(k + 1) {
+            *pos += 1.0;
+        }
+
+        // The canonical desired-position formula `np_i = 1 + (N - 1) * p_i`,
+        // where the five markers target quantiles `p = [0, q/2, q, (1+q)/2,
+        // 1]`; marker 0 stays pinned at 1 (its `p_1 = 0` makes the formula a
+        // no-op) and marker 4 tracks `count` (its `p_5 = 1`).
+        let n = self.count as f64 - 1.0;
+        self.desired[4] = self.count as f64;
+        self.desired[1] = 1.0 + n * (self.q / 2.0);
+        self.desired[2] = 1.0 + n * self.q;
+        self.desired[3] = 1.0 + n * (1.0 + self.q) / 2.0;
+
+        for i in 1..4 {
+            let d = self.desired[i] - self.positions[i];
+            if (d >= 1.0 && self.positions[i + 1] - self.positions[i] > 1.0)
+                || (d <= -1.0 && self.positions[i - 1] - self.positions[i] < -1.0)
+            {
+                let sign = if d >= 0.0 { 1.0 } else { -1.0 };
+                let parabolic = self.parabolic(i, sign);
+                let new_height = if self.heights[i - 1] < parabolic && parabolic < self.heights[i + 1] {
+                    parabolic
+                } else {
+                    self.linear(i, sign)
+                };
+                self.heights[i] = new_height;
+                self.positions[i] += sign;
+            }
+        }
+    }
+
+    fn parabolic(&self, i: usize, sign: f64) -> f64 {
+        let (q_im1, q_i, q_ip1) = (self.heights[i - 1], self.heights[i], self.heights[i + 1]);
+        let (n_im1, n_i, n_ip1) = (self.positions[i - 1], self.positions[i], self.positions[i + 1]);
+        q_i + sign / (n_ip1 - n_im1)
+            * ((n_i - n_im1 + sign) * (q_ip1 - q_i) / (n_ip1 - n_i)
+                + (n_ip1 - n_i - sign) * (q_i - q_im1) / (n_i - n_im1))
+    }
+
+    fn linear(&self, i: usize, sign: f64) -> f64 {
+        let j = if sign > 0.0 { i + 1 } else { i - 1 };
+        self.heights[i] + sign * (self.heights[j] - self.heights[i]) / (self.positions[j] - self.positions[i])
+    }
+
+    pub fn quantile(&self) -> f64 {
+        if self.count == 0 {
+            return f64::NAN;
+        }
+        if self.init.len() < 5 {
+            // Too few samples for the marker scheme; fall back to the exact
+            // quantile over what little data there is.
+            let mut sorted = self.init.clone();
+            sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            let idx = ((sorted.len() - 1) as f64 * self.q).round() as usize;
+            return sorted[idx];
+        }
+        self.heights[2]
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl QuantileEstimator for PSquareQuantileState {
+    fn insert(&mut self, x: f64) {
+        PSquareQuantileState::insert(self, x);
+    }
+
+    /// `q` is ignored; this backend only ever answers the quantile it was
+    /// constructed with. Use [`PSquareMulti`] to track several at once.
+    fn quantile(&self, _q: f64) -> f64 {
+        self.quantile()
+    }
+
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+/// Several [`PSquareQuantileState`]s sharing one input stream, for tracking
+/// e.g. p50/p95/p99 simultaneously without re-reading the data.
+#[derive(Debug)]
+pub struct PSquareMulti {
+    states: Vec<PSquareQuantileState>,
+}
+
+impl PSquareMulti {
+    pub fn new(qs: &[f64]) -> Self {
+        Self {
+            states: qs.iter().map(|&q| PSquareQuantileState::new(q)).collect(),
+        }
+    }
+
+    pub fn insert(&mut self, x: f64) {
+        for state in &mut self.states {
+            state.insert(x);
+        }
+    }
+
+    /// The tracked quantiles in the same order passed to [`new`](Self::new).
+    pub fn quantiles(&self) -> Vec<f64> {
+        self.states.iter().map(|s| s.quantile()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quantile_brute::exact_quantile;
+    use crate::stats::QuantileInterpolation;
+
+    fn log_normal_sample(n: usize) -> Vec<f64> {
+        // A small xorshift PRNG, deterministic across test runs, shaping a
+        // heavy right tail the way real latency distributions do.
+        let mut state: u64 = 0x2545_F491_4F6C_DD1D;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 11) as f64 / ((1u64 << 53) as f64)
+        };
+        (0..n)
+            .map(|_| {
+                let u1 = next().max(1e-12);
+                let u2 = next();
+                let z = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+                (z * 0.5).exp() * 100.0
+            })
+            .collect()
+    }
+
+    #[test]
+    fn approximates_median_within_tolerance_on_log_normal_sample() {
+        let data = log_normal_sample(100_000);
+        let mut state = PSquareQuantileState::new(0.5);
+        for &x in &data {
+            state.insert(x);
+        }
+        let approx = state.quantile();
+        let exact = exact_quantile(&data, 0.5, QuantileInterpolation::Linear);
+        assert!((approx - exact).abs() < exact * 0.15, "approx={approx} exact={exact}");
+    }
+
+    #[test]
+    fn approximates_p99_within_tolerance_on_log_normal_sample() {
+        let data = log_normal_sample(100_000);
+        let mut state = PSquareQuantileState::new(0.99);
+        for &x in &data {
+            state.insert(x);
+        }
+        let approx = state.quantile();
+        let exact = exact_quantile(&data, 0.99, QuantileInterpolation::Linear);
+        assert!((approx - exact).abs() < exact * 0.25, "approx={approx} exact={exact}");
+    }
+
+    #[test]
+    fn multi_tracks_several_quantiles_in_one_pass() {
+        let data = log_normal_sample(20_000);
+        let mut multi = PSquareMulti::new(&[0.5, 0.9, 0.99]);
+        for &x in &data {
+            multi.insert(x);
+        }
+        let results = multi.quantiles();
+        assert_eq!(results.len(), 3);
+        assert!(results[0] < results[1] && results[1] < results[2]);
+    }
+
+    #[test]
+    fn handles_fewer_than_five_samples() {
+        let mut state = PSquareQuantileState::new(0.5);
+        state.insert(1.0);
+        state.insert(2.0);
+        assert_eq!(state.count(), 2);
+        assert!(state.quantile().is_finite());
+    }
+}
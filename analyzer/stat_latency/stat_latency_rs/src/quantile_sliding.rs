@@ -0,0 +1,106 @@
+//! Fixed-size ring buffer of the most recent samples, for quantiles over a
+//! trailing window of wall-clock time rather than the whole run. Latency
+//! often degrades mid-test; a single aggregate `Statistics` hides that, while
+//! `SlidingWindowQuantile::quantile` sampled periodically traces p99 over
+//! time.
+
+use std::cmp::Ordering;
+
+/// Quantiles over the last `window` inserted samples. The first version
+/// sorts on demand at query time rather than maintaining an order
+/// statistics structure incrementally; callers querying every sample should
+/// expect `O(window log window)` per query.
+#[derive(Debug)]
+pub struct SlidingWindowQuantile {
+    window: usize,
+    /// Ring buffer of the last `window` samples; `next` is the index the
+    /// following insert will overwrite.
+    buffer: Vec<f64>,
+    next: usize,
+    filled: bool,
+}
+
+impl SlidingWindowQuantile {
+    pub fn new(window: usize) -> Self {
+        assert!(window >= 1, "window must be >= 1");
+        Self {
+            window,
+            buffer: Vec::with_capacity(window),
+            next: 0,
+            filled: false,
+        }
+    }
+
+    /// Insert a sample, evicting the oldest once the window is full.
+    pub fn insert(&mut self, x: f64) {
+        if self.buffer.len() < self.window {
+            self.buffer.push(x);
+        } else {
+            self.buffer[self.next] = x;
+            self.filled = true;
+        }
+        self.next = (self.next + 1) % self.window;
+    }
+
+    /// The exact quantile over whatever is currently in the window (which may
+    /// be fewer than `window` samples early on).
+    pub fn quantile(&self, q: f64) -> f64 {
+        if self.buffer.is_empty() {
+            return f64::NAN;
+        }
+        let mut sorted = self.buffer.clone();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        let q = q.clamp(0.0, 1.0);
+        let h = (sorted.len() - 1) as f64 * q;
+        let lo = h.floor() as usize;
+        let hi = h.ceil() as usize;
+        if lo == hi {
+            return sorted[lo];
+        }
+        let w = h - (lo as f64);
+        sorted[lo] + (sorted[hi] - sorted[lo]) * w
+    }
+
+    /// Number of samples currently held (`< window` until the window fills).
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// Whether the window has been fully populated at least once.
+    pub fn is_full(&self) -> bool {
+        self.filled || self.buffer.len() == self.window
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evicts_oldest_once_full() {
+        let mut window = SlidingWindowQuantile::new(3);
+        window.insert(1.0);
+        window.insert(2.0);
+        window.insert(3.0);
+        assert!(window.is_full());
+        assert_eq!(window.quantile(1.0), 3.0);
+        window.insert(100.0);
+        // The oldest sample (1.0) was evicted; the window is now [2, 3, 100].
+        assert_eq!(window.quantile(0.0), 2.0);
+        assert_eq!(window.quantile(1.0), 100.0);
+    }
+
+    #[test]
+    fn answers_quantiles_before_full() {
+        let mut window = SlidingWindowQuantile::new(10);
+        window.insert(5.0);
+        window.insert(10.0);
+        assert!(!window.is_full());
+        assert_eq!(window.len(), 2);
+        assert_eq!(window.quantile(1.0), 10.0);
+    }
+}
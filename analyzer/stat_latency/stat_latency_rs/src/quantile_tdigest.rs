@@ -1,45 +1,985 @@
-use tdigests::TDigest;
+use std::fmt;
+
+use serde::de::Deserializer;
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+use tdigests::{Centroid, TDigest};
+
+use crate::estimator::QuantileEstimator;
+
+/// Force a percentile grid non-decreasing by clamping each value up to the
+/// previous one whenever it dips below (or is `NaN` while a real floor is
+/// already established). Centroid interpolation can return a slightly
+/// out-of-order result near the tail — e.g. p99 a hair below p95 — which is
+/// an approximation artifact, not a real crossing of percentiles; this is a
+/// cosmetic correction for reporting a grid, not a fix to the underlying
+/// per-`q` estimate. `values` is assumed to already be in increasing-`q`
+/// order; a `NaN` before any real value is left as `NaN` rather than
+/// clamped to nothing.
+pub fn monotonic(values: &[f64]) -> Vec<f64> {
+    let mut out = Vec::with_capacity(values.len());
+    let mut floor = f64::NAN;
+    for &value in values {
+        let clamped = if !floor.is_nan() && (value.is_nan() || value < floor) {
+            floor
+        } else {
+            value
+        };
+        if !clamped.is_nan() {
+            floor = clamped;
+        }
+        out.push(clamped);
+    }
+    out
+}
+
+/// Default flush threshold floor: merge the buffer into the digest at least
+/// every 200 inserts, more often once the adaptive threshold in
+/// [`flush_threshold`](TDigestQuantileState::flush_threshold) grows past it.
+const DEFAULT_BUFFER_CAPACITY: usize = 200;
+/// Default centroid bound after compression.
+const DEFAULT_COMPRESSION: usize = 2000;
+
+/// Tuning knobs for [`TDigestQuantileState::with_config`].
+///
+/// `buffer_capacity` is a *floor* on the flush threshold, not a fixed value:
+/// see [`flush_threshold`](TDigestQuantileState::flush_threshold) for how it
+/// grows with the stream. It trades ingest throughput for buffer memory
+/// early in a stream: a larger floor amortises the merge/compress cost over
+/// more inserts, at the cost of holding more raw samples before they're
+/// folded in. `compression` trades digest accuracy for memory: a smaller
+/// centroid bound uses less memory per state but widens the quantile error,
+/// most noticeably at the extreme tails — and, since the adaptive threshold
+/// is `count / compression`, a smaller `compression` also means merges get
+/// more frequent sooner as the stream grows.
+#[derive(Debug, Clone, Copy)]
+pub struct TDigestConfig {
+    pub buffer_capacity: usize,
+    pub compression: usize,
+}
+
+impl Default for TDigestConfig {
+    fn default() -> Self {
+        Self {
+            buffer_capacity: DEFAULT_BUFFER_CAPACITY,
+            compression: DEFAULT_COMPRESSION,
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct TDigestQuantileState {
     digest: Option<TDigest>,
     buffer: Vec<f64>,
+    count: usize,
+    buffer_capacity: usize,
+    compression: usize,
 }
 
 impl TDigestQuantileState {
     pub fn new(_expected_count: usize) -> Self {
+        Self::with_config(TDigestConfig::default())
+    }
+
+    /// Like [`new`](Self::new) but with an explicit [`TDigestConfig`].
+    /// Panics if `buffer_capacity` or `compression` is zero — a zero buffer
+    /// would merge on every insert with no batching, and a zero-centroid
+    /// digest could never answer a quantile.
+    pub fn with_config(config: TDigestConfig) -> Self {
+        assert!(config.buffer_capacity >= 1, "buffer_capacity must be >= 1");
+        assert!(config.compression >= 1, "compression must be >= 1");
         Self {
             digest: None,
-            buffer: vec![],
+            buffer: Vec::with_capacity(config.buffer_capacity),
+            count: 0,
+            buffer_capacity: config.buffer_capacity,
+            compression: config.compression,
         }
     }
 
     pub fn insert(&mut self, x: f64) {
         self.buffer.push(x);
-        if self.buffer.len() >= 200 {
+        self.count += 1;
+        if self.buffer.len() >= self.flush_threshold() {
+            self.merge();
+        }
+    }
+
+    /// Insert many values at once. Checks the flush threshold once per call
+    /// rather than once per element: values are appended straight into the
+    /// buffer, and if that crosses [`flush_threshold`](Self::flush_threshold)
+    /// the whole buffer is merged in a single `TDigest::from_values` pass,
+    /// matching the result of repeated [`insert`](Self::insert) calls but
+    /// with far less per-element overhead.
+    pub fn extend(&mut self, values: impl IntoIterator<Item = f64>) {
+        let before = self.buffer.len();
+        self.buffer.extend(values);
+        self.count += self.buffer.len() - before;
+        if self.buffer.len() >= self.flush_threshold() {
             self.merge();
         }
     }
 
+    /// How many buffered samples it currently takes to trigger a flush:
+    /// `max(buffer_capacity, count / compression)`. Fixed at
+    /// `buffer_capacity` while the stream is small — merging a tiny digest
+    /// on every 200 samples is cheap — then grows proportionally to `count`
+    /// so a huge stream doesn't keep paying for merges every 200 samples out
+    /// of millions. `compression` sets how fast it grows: the threshold
+    /// reaches `compression` centroids' worth of buffered samples once
+    /// `count` reaches `compression^2`.
+    pub fn flush_threshold(&self) -> usize {
+        self.buffer_capacity.max(self.count / self.compression)
+    }
+
+    /// Like [`insert`](Self::insert) but skipping NaN/+-inf samples, returning
+    /// whether `x` was accepted.
+    pub fn insert_checked(&mut self, x: f64) -> bool {
+        if x.is_finite() {
+            self.insert(x);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Number of samples sitting in the unflushed buffer, not yet folded into
+    /// the digest. Useful for monitoring memory or deciding to force a
+    /// [`flush`](Self::flush) before serializing.
+    pub fn pending(&self) -> usize {
+        self.buffer.len()
+    }
+
     pub fn merge(&mut self) {
         if self.buffer.is_empty() {
             return;
         }
 
-        let incoming =
-            TDigest::from_values(std::mem::replace(&mut self.buffer, Vec::with_capacity(300)));
+        let incoming = TDigest::from_values(std::mem::replace(
+            &mut self.buffer,
+            Vec::with_capacity(self.buffer_capacity),
+        ));
         let mut merged = match self.digest.take() {
             Some(existing) => existing.merge(&incoming),
             None => incoming,
         };
-        merged.compress(2000);
+        merged.compress(self.compression);
         self.digest = Some(merged);
     }
 
+    /// Preferred name for [`merge`](Self::merge): folds the buffer into the
+    /// digest. `merge` is a confusing name here since it doesn't merge two
+    /// states together, it just flushes this one's buffer — kept for
+    /// backward compatibility.
+    pub fn flush(&mut self) {
+        self.merge();
+    }
+
+    /// Reads any buffered-but-unflushed samples into a throwaway digest first
+    /// (via [`as_digest`](Self::as_digest)), so a single inserted sample that
+    /// hasn't crossed `buffer_capacity` yet still answers every `q` with that
+    /// sample rather than `NaN` — matching [`rank`](Self::rank) and
+    /// [`centroids`](Self::centroids), which already account for the buffer.
     pub fn quantile(&self, q: f64) -> f64 {
-        self.digest
-            .as_ref()
+        self.as_digest()
             .map(|d| d.estimate_quantile(q))
             .unwrap_or(f64::NAN)
     }
+
+    /// [`quantile`](Self::quantile) for every `q` in `qs`, in the same
+    /// order, passed through [`monotonic`] so the results never dip below
+    /// where `qs` calls for them to be non-decreasing. `qs` need not
+    /// already be sorted, but the returned sequence is only guaranteed
+    /// non-decreasing relative to *this call's* ordering of `qs`, not some
+    /// canonical one — sort `qs` first if that matters.
+    pub fn quantile_many(&self, qs: &[f64]) -> Vec<f64> {
+        let raw: Vec<f64> = qs.iter().map(|&q| self.quantile(q)).collect();
+        monotonic(&raw)
+    }
+
+    /// `n` evenly spaced `(value, cumulative_probability)` points tracing the
+    /// digest's approximate CDF, matching
+    /// [`stats::cdf_points`](crate::stats::cdf_points)'s contract so callers
+    /// can draw the same curve regardless of which backend produced it.
+    /// Empty on an empty digest or `n < 2`.
+    pub fn cdf_points(&self, n: usize) -> Vec<(f64, f64)> {
+        if self.is_empty() || n < 2 {
+            return Vec::new();
+        }
+        (0..n)
+            .map(|i| {
+                let q = i as f64 / (n - 1) as f64;
+                (self.quantile(q), q)
+            })
+            .collect()
+    }
+
+    /// The approximate CDF at `value`: what quantile `value` sits at,
+    /// complementing [`quantile`](Self::quantile). Reads any buffered
+    /// samples into a throwaway digest rather than mutating `self`, so a
+    /// `&self` query always sees the full stream.
+    pub fn rank(&self, value: f64) -> f64 {
+        match self.as_digest() {
+            Some(d) => d.estimate_rank(value),
+            None => f64::NAN,
+        }
+    }
+
+    /// Drop the digest and any buffered samples, and reset the running
+    /// count, so this state can be reused for the next time window.
+    pub fn clear(&mut self) {
+        self.digest = None;
+        self.buffer.clear();
+        self.count = 0;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Total inserted sample count, covering both merged-into-the-digest and
+    /// still-buffered samples (`TDigest` itself doesn't track this, so it's
+    /// a running counter kept alongside it).
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Approximate heap footprint in bytes: the digest's raw centroid count
+    /// times `size_of::<Centroid>()`, plus the still-buffered samples'
+    /// allocated capacity. Reads the digest's centroids directly rather than
+    /// going through [`centroids`](Self::centroids), which recompresses on
+    /// every call, so this stays cheap enough to call from a hot
+    /// flush-decision loop. For a coordinator merging thousands of states,
+    /// this is a far better capacity signal than guessing by sample count,
+    /// since a compressed digest's size depends on `compression`, not `len`.
+    pub fn memory_bytes(&self) -> usize {
+        let centroid_count = self.digest.as_ref().map_or(0, |d| d.centroids().len());
+        let centroids_bytes = centroid_count * std::mem::size_of::<Centroid>();
+        let buffer_bytes = self.buffer.len() * std::mem::size_of::<f64>();
+        std::mem::size_of::<Self>() + centroids_bytes + buffer_bytes
+    }
+
+    /// Snapshot the state as a flat `(mean, weight)` centroid list. Any samples
+    /// still in the buffer are emitted as unit-weight centroids so the snapshot
+    /// is complete without mutating `self`.
+    pub fn to_centroids(&self) -> Vec<(f64, f64)> {
+        let mut out: Vec<(f64, f64)> = self
+            .digest
+            .as_ref()
+            .map(|d| d.centroids().iter().map(|c| (c.mean(), c.weight())).collect())
+            .unwrap_or_default();
+        out.extend(self.buffer.iter().map(|&v| (v, 1.0)));
+        out
+    }
+
+    /// Rebuild a state from a centroid list and its total sample count,
+    /// re-compressing to the default centroid bound. The serialized form
+    /// doesn't carry the original [`TDigestConfig`], so a restored state
+    /// always uses the defaults; call [`with_config`](Self::with_config) and
+    /// merge in if a custom config must be preserved across a checkpoint.
+    pub fn from_centroids(centroids: Vec<(f64, f64)>, count: usize) -> Self {
+        // A zero-weight centroid carries no mass and the underlying
+        // `tdigests` crate panics if every centroid it's given is like that
+        // (e.g. a caller decaying weights down to nothing), so they're
+        // dropped here rather than passed through.
+        let cs: Vec<Centroid> = centroids
+            .into_iter()
+            .filter(|&(_, weight)| weight > 0.0)
+            .map(|(mean, weight)| Centroid::new(mean, weight))
+            .collect();
+        let digest = if cs.is_empty() {
+            None
+        } else {
+            let mut d = TDigest::from_centroids(cs);
+            d.compress(DEFAULT_COMPRESSION);
+            Some(d)
+        };
+        Self {
+            digest,
+            buffer: vec![],
+            count,
+            buffer_capacity: DEFAULT_BUFFER_CAPACITY,
+            compression: DEFAULT_COMPRESSION,
+        }
+    }
+
+    /// The digest's internal centroids as `(mean, weight)` pairs, after
+    /// folding in and compressing any buffered-but-unflushed samples (without
+    /// mutating `self`). For debugging accuracy problems: a healthy digest
+    /// stays near [`compression`](TDigestConfig::compression) centroids
+    /// regardless of how many samples it has seen, rather than keeping one
+    /// centroid per unique value.
+    pub fn centroids(&self) -> Vec<(f64, f64)> {
+        match self.as_digest() {
+            Some(mut d) => {
+                d.compress(self.compression);
+                d.centroids().iter().map(|c| (c.mean(), c.weight())).collect()
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// The number of centroids currently held, i.e. `self.centroids().len()`.
+    pub fn centroid_count(&self) -> usize {
+        self.centroids().len()
+    }
+
+    /// `quantile(q)` alongside a rough estimated rank error, as a fraction of
+    /// `count()`: half the weight of the centroid bracketing the target rank,
+    /// which is the most the estimate could be off by before spilling into
+    /// the neighbouring centroid. This is a coarse bound derived from local
+    /// centroid density, not the digest's true worst-case error — use it to
+    /// decide whether an estimate needs a brute-force double-check, not as an
+    /// exact confidence interval. `NaN` error on an empty digest.
+    pub fn quantile_with_error(&self, q: f64) -> (f64, f64) {
+        let q = q.clamp(0.0, 1.0);
+        let estimate = self.quantile(q);
+        let count = self.count() as f64;
+        let centroids = self.centroids();
+        if count == 0.0 || centroids.is_empty() {
+            return (estimate, f64::NAN);
+        }
+
+        let target_rank = q * count;
+        let mut cumulative = 0.0;
+        let mut bracket_weight = centroids[0].1;
+        for &(_, weight) in &centroids {
+            cumulative += weight;
+            bracket_weight = weight;
+            if cumulative >= target_rank {
+                break;
+            }
+        }
+        (estimate, (bracket_weight / 2.0) / count)
+    }
+
+    /// Materialize this state (digest plus any buffered samples) into a single
+    /// `TDigest`, without disturbing `self`.
+    fn as_digest(&self) -> Option<TDigest> {
+        let centroids = self.to_centroids();
+        if centroids.is_empty() {
+            return None;
+        }
+        let cs = centroids
+            .into_iter()
+            .map(|(mean, weight)| Centroid::new(mean, weight))
+            .collect();
+        Some(TDigest::from_centroids(cs))
+    }
+
+    /// Combine another worker's digest into this one: flush both buffers, merge
+    /// the underlying `TDigest`s, and re-compress to `self`'s centroid bound.
+    pub fn merge_state(&mut self, other: &TDigestQuantileState) {
+        self.merge();
+        let combined = match (self.digest.take(), other.as_digest()) {
+            (Some(a), Some(b)) => Some(a.merge(&b)),
+            (Some(a), None) => Some(a),
+            (None, Some(b)) => Some(b),
+            (None, None) => None,
+        };
+        if let Some(mut d) = combined {
+            d.compress(self.compression);
+            self.digest = Some(d);
+        }
+        self.count += other.count;
+    }
+}
+
+/// Tree-reduction merge of many independently-built digests into one, for the
+/// final aggregation step of a fan-out run producing hundreds of per-worker
+/// `TDigestQuantileState`s. Every state is flushed first so no buffered
+/// sample is lost, then states are paired up and merged bottom-up rather
+/// than folded one at a time onto a single accumulator, which is what makes
+/// [`merge_many_parallel`] possible. `compress` still runs once per pairwise
+/// merge either way. The result's quantiles match a linear fold over the
+/// same states within normal t-digest tolerance, since `TDigest` merging is
+/// associative up to compression's approximation. Returns an empty state for
+/// an empty `states`.
+pub fn merge_many(mut states: Vec<TDigestQuantileState>) -> TDigestQuantileState {
+    if states.is_empty() {
+        return TDigestQuantileState::new(0);
+    }
+    for state in &mut states {
+        state.merge();
+    }
+    while states.len() > 1 {
+        let mut next = Vec::with_capacity((states.len() + 1) / 2);
+        let mut iter = states.into_iter();
+        while let Some(mut a) = iter.next() {
+            if let Some(b) = iter.next() {
+                a.merge_state(&b);
+            }
+            next.push(a);
+        }
+        states = next;
+    }
+    states.into_iter().next().unwrap()
+}
+
+/// Like [`merge_many`] but parallelizing the pairwise reduction with rayon,
+/// for the hundreds-of-states case where the sequential tree walk itself
+/// becomes the bottleneck. Produces the same result as [`merge_many`] up to
+/// t-digest tolerance, since the pairing order doesn't matter to an
+/// associative merge.
+#[cfg(feature = "rayon")]
+pub fn merge_many_parallel(states: Vec<TDigestQuantileState>) -> TDigestQuantileState {
+    use rayon::prelude::*;
+
+    let mut states = states;
+    for state in &mut states {
+        state.merge();
+    }
+    states
+        .into_par_iter()
+        .reduce(|| TDigestQuantileState::new(0), |mut a, b| {
+            a.merge_state(&b);
+            a
+        })
+}
+
+/// Serde wire form: the centroid list plus the total sample count.
+#[derive(Serialize, Deserialize)]
+struct DigestRepr {
+    centroids: Vec<(f64, f64)>,
+    count: usize,
+}
+
+impl Serialize for TDigestQuantileState {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        DigestRepr {
+            centroids: self.to_centroids(),
+            count: self.count,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TDigestQuantileState {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = DigestRepr::deserialize(deserializer)?;
+        Ok(Self::from_centroids(repr.centroids, repr.count))
+    }
+}
+
+impl TDigestQuantileState {
+    /// Checkpoint this state to a byte blob via the [`Serialize`] impl above
+    /// (which already folds any buffered-but-unflushed samples into the
+    /// snapshot), so a long-running test can persist and resume a digest
+    /// across a crash.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("TDigestQuantileState serialization is infallible")
+    }
+
+    /// Restore a checkpoint written by [`to_bytes`](Self::to_bytes). A
+    /// round-trip preserves quantile answers exactly: the centroids and
+    /// compression are carried verbatim through [`Serialize`]/[`Deserialize`].
+    pub fn from_bytes(data: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(data)
+    }
+
+    /// Deserialize each of `chunks` (as produced by [`to_bytes`](Self::to_bytes)
+    /// on a per-node digest) and fold them into one state via
+    /// [`merge_many`], for a coordinator reconstructing a global digest from
+    /// bytes shipped over the network rather than raw samples. Fails on the
+    /// first chunk that doesn't deserialize, reporting its index into
+    /// `chunks` so the caller can tell which node sent bad data.
+    pub fn merge_from_bytes(chunks: &[Vec<u8>]) -> Result<TDigestQuantileState, DeserializeError> {
+        let states = chunks
+            .iter()
+            .enumerate()
+            .map(|(chunk_index, bytes)| {
+                TDigestQuantileState::from_bytes(bytes)
+                    .map_err(|source| DeserializeError { chunk_index, source })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(merge_many(states))
+    }
+}
+
+/// Failure reconstructing a [`TDigestQuantileState`] from serialized chunks
+/// in [`TDigestQuantileState::merge_from_bytes`], identifying which chunk
+/// (by index into the input slice) failed to deserialize so the caller can
+/// tell which node shipped bad data.
+#[derive(Debug)]
+pub struct DeserializeError {
+    pub chunk_index: usize,
+    pub source: serde_json::Error,
+}
+
+impl fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to deserialize digest chunk {}: {}", self.chunk_index, self.source)
+    }
+}
+
+impl std::error::Error for DeserializeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+impl QuantileEstimator for TDigestQuantileState {
+    fn insert(&mut self, x: f64) {
+        TDigestQuantileState::insert(self, x);
+    }
+
+    fn quantile(&self, q: f64) -> f64 {
+        TDigestQuantileState::quantile(self, q)
+    }
+
+    fn count(&self) -> usize {
+        self.count
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.merge_state(other);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pending_tracks_the_unflushed_buffer_and_flush_clears_it() {
+        let config = TDigestConfig {
+            buffer_capacity: 1_000,
+            compression: DEFAULT_COMPRESSION,
+        };
+        let mut state = TDigestQuantileState::with_config(config);
+        for i in 1..=10 {
+            state.insert(i as f64);
+        }
+        assert_eq!(state.pending(), 10);
+        state.flush();
+        assert_eq!(state.pending(), 0);
+        assert_eq!(state.count(), 10);
+    }
+
+    #[test]
+    fn flush_threshold_stays_at_the_floor_while_the_stream_is_small() {
+        let config = TDigestConfig { buffer_capacity: 200, compression: 2_000 };
+        let state = TDigestQuantileState::with_config(config);
+        assert_eq!(state.flush_threshold(), 200);
+
+        let mut state = state;
+        for i in 1..=1_000 {
+            state.insert(i as f64);
+        }
+        // 1_000 / 2_000 rounds down to 0, so the floor still governs.
+        assert_eq!(state.flush_threshold(), 200);
+    }
+
+    #[test]
+    fn flush_threshold_grows_proportionally_with_count() {
+        let config = TDigestConfig { buffer_capacity: 200, compression: 100 };
+        let mut state = TDigestQuantileState::with_config(config);
+        for i in 1..=50_000u64 {
+            state.insert(i as f64);
+        }
+        // 50_000 / 100 = 500, well past the 200 floor.
+        assert_eq!(state.flush_threshold(), 500);
+    }
+
+    #[test]
+    fn adaptive_threshold_stays_within_tdigest_tolerance_on_a_huge_stream() {
+        // 200_000 samples against a compression of 100 pushes flush_threshold
+        // well past the 200 floor (200_000 / 100 = 2_000), so this stream
+        // merges far less often than the fixed-threshold behavior would have.
+        // The quantile should still land within ordinary t-digest error.
+        let data: Vec<f64> = (1..=200_000u64).map(|i| i as f64).collect();
+        let mut state = TDigestQuantileState::with_config(TDigestConfig {
+            buffer_capacity: 200,
+            compression: 100,
+        });
+        for &x in &data {
+            state.insert(x);
+        }
+        state.flush();
+        let p99 = state.quantile(0.99);
+        assert!((p99 - 198_000.0).abs() < 198_000.0 * 0.05, "p99 was {p99}");
+    }
+
+    #[test]
+    fn recovers_tail_quantile() {
+        let mut state = TDigestQuantileState::new(10_000);
+        for i in 1..=10_000 {
+            state.insert(i as f64);
+        }
+        state.merge();
+        let p99 = state.quantile(0.99);
+        assert!((p99 - 9_900.0).abs() < 150.0, "p99 was {p99}");
+    }
+
+    #[test]
+    fn memory_bytes_is_nonzero_and_bounded_by_compression_once_merged() {
+        let mut state = TDigestQuantileState::new(10_000);
+        for i in 1..=10_000 {
+            state.insert(i as f64);
+        }
+        state.merge();
+        assert!(state.memory_bytes() > 0);
+        // A compressed digest stays near `compression` centroids regardless
+        // of how many samples it has seen, so a generous multiple of that
+        // bounds the centroid contribution to memory_bytes.
+        let bound = DEFAULT_COMPRESSION * 4 * std::mem::size_of::<Centroid>()
+            + std::mem::size_of::<TDigestQuantileState>();
+        assert!(state.memory_bytes() < bound, "memory_bytes was {}", state.memory_bytes());
+    }
+
+    #[test]
+    fn memory_bytes_reflects_unflushed_buffer_growth() {
+        let mut state = TDigestQuantileState::with_config(TDigestConfig {
+            buffer_capacity: 100_000,
+            ..TDigestConfig::default()
+        });
+        let empty = state.memory_bytes();
+        for i in 0..1_000 {
+            state.insert(i as f64);
+        }
+        assert!(state.memory_bytes() > empty);
+    }
+
+    #[test]
+    fn merge_state_matches_single() {
+        let mut whole = TDigestQuantileState::new(10_000);
+        let mut left = TDigestQuantileState::new(5_000);
+        let mut right = TDigestQuantileState::new(5_000);
+        for i in 1..=10_000 {
+            whole.insert(i as f64);
+            if i <= 5_000 {
+                left.insert(i as f64);
+            } else {
+                right.insert(i as f64);
+            }
+        }
+        left.merge_state(&right);
+        assert_eq!(left.count(), whole.count());
+        let (a, b) = (left.quantile(0.99), whole.quantile(0.99));
+        assert!((a - b).abs() < 150.0, "merged {a} vs whole {b}");
+    }
+
+    #[test]
+    fn extend_matches_repeated_insert() {
+        let mut one_by_one = TDigestQuantileState::new(0);
+        for i in 1..=5_000 {
+            one_by_one.insert(i as f64);
+        }
+        one_by_one.merge();
+        let mut extended = TDigestQuantileState::new(0);
+        extended.extend((1..=5_000).map(|i| i as f64));
+        extended.merge();
+        assert_eq!(extended.len(), one_by_one.len());
+        let (a, b) = (extended.quantile(0.99), one_by_one.quantile(0.99));
+        assert!((a - b).abs() < 10.0, "extended {a} vs one-by-one {b}");
+    }
+
+    #[test]
+    fn with_config_honours_custom_buffer_and_compression() {
+        let mut state = TDigestQuantileState::with_config(TDigestConfig {
+            buffer_capacity: 10,
+            compression: 50,
+        });
+        for i in 1..=1_000 {
+            state.insert(i as f64);
+        }
+        let p99 = state.quantile(0.99);
+        assert!((p99 - 990.0).abs() < 50.0, "p99 was {p99}");
+    }
+
+    #[test]
+    #[should_panic(expected = "buffer_capacity")]
+    fn with_config_rejects_zero_buffer_capacity() {
+        TDigestQuantileState::with_config(TDigestConfig {
+            buffer_capacity: 0,
+            compression: 100,
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "compression")]
+    fn with_config_rejects_zero_compression() {
+        TDigestQuantileState::with_config(TDigestConfig {
+            buffer_capacity: 100,
+            compression: 0,
+        });
+    }
+
+    #[test]
+    fn clear_resets_to_empty() {
+        let mut state = TDigestQuantileState::new(10_000);
+        for i in 1..=500 {
+            state.insert(i as f64);
+        }
+        assert!(!state.is_empty());
+        assert_eq!(state.len(), 500);
+        state.clear();
+        assert!(state.is_empty());
+        assert_eq!(state.len(), 0);
+        assert!(state.quantile(0.5).is_nan());
+    }
+
+    #[test]
+    fn rank_matches_brute_within_tolerance() {
+        use crate::quantile_brute::BruteQuantileState;
+
+        let mut digest = TDigestQuantileState::new(10_000);
+        let mut brute = BruteQuantileState::new();
+        for i in 1..=10_000 {
+            digest.insert(i as f64);
+            brute.insert(i as f64);
+        }
+        digest.merge();
+        let approx = digest.rank(9_900.0);
+        let exact = brute.rank(9_900.0);
+        assert!((approx - exact).abs() < 0.02, "approx {approx} vs exact {exact}");
+
+        assert!(TDigestQuantileState::new(0).rank(1.0).is_nan());
+    }
+
+    #[test]
+    fn bytes_round_trip_preserves_quantiles() {
+        let mut state = TDigestQuantileState::new(10_000);
+        for i in 1..=10_000 {
+            state.insert(i as f64);
+        }
+        let bytes = state.to_bytes();
+        let restored = TDigestQuantileState::from_bytes(&bytes).expect("valid checkpoint");
+        assert_eq!(restored.count(), state.count());
+        assert_eq!(restored.quantile(0.99), state.quantile(0.99));
+        assert_eq!(restored.quantile(0.5), state.quantile(0.5));
+    }
+
+    #[test]
+    fn merge_from_bytes_matches_merge_many_of_the_originals() {
+        let mut left = TDigestQuantileState::new(5_000);
+        let mut right = TDigestQuantileState::new(5_000);
+        for i in 1..=5_000 {
+            left.insert(i as f64);
+        }
+        for i in 5_001..=10_000 {
+            right.insert(i as f64);
+        }
+        let chunks = vec![left.to_bytes(), right.to_bytes()];
+        let merged = TDigestQuantileState::merge_from_bytes(&chunks).expect("both chunks are valid");
+
+        let expected = merge_many(vec![left, right]);
+        assert_eq!(merged.count(), expected.count());
+        assert_eq!(merged.quantile(0.99), expected.quantile(0.99));
+    }
+
+    #[test]
+    fn merge_from_bytes_reports_the_index_of_a_malformed_chunk() {
+        let good = TDigestQuantileState::new(0).to_bytes();
+        let chunks = vec![good.clone(), b"not json".to_vec(), good];
+        let err = TDigestQuantileState::merge_from_bytes(&chunks).expect_err("middle chunk is malformed");
+        assert_eq!(err.chunk_index, 1);
+    }
+
+    #[test]
+    fn insert_checked_rejects_non_finite() {
+        let mut state = TDigestQuantileState::new(10);
+        assert!(state.insert_checked(1.0));
+        assert!(!state.insert_checked(f64::NAN));
+        assert!(!state.insert_checked(f64::NEG_INFINITY));
+        assert_eq!(state.count(), 1);
+    }
+
+    #[test]
+    fn centroids_stay_near_compression_bound() {
+        let mut state = TDigestQuantileState::with_config(TDigestConfig {
+            buffer_capacity: 50,
+            compression: 100,
+        });
+        for i in 1..=20_000 {
+            state.insert(i as f64);
+        }
+        let count = state.centroid_count();
+        assert!(count <= 200, "centroid_count {count} far exceeds the compression bound");
+        assert_eq!(state.centroids().len(), count);
+    }
+
+    #[test]
+    fn centroids_empty_for_fresh_state() {
+        let state = TDigestQuantileState::new(0);
+        assert!(state.centroids().is_empty());
+        assert_eq!(state.centroid_count(), 0);
+    }
+
+    #[test]
+    fn quantile_with_error_matches_quantile_and_bounds_shrink_with_more_centroids() {
+        let mut coarse = TDigestQuantileState::with_config(TDigestConfig {
+            buffer_capacity: 50,
+            compression: 20,
+        });
+        let mut fine = TDigestQuantileState::with_config(TDigestConfig {
+            buffer_capacity: 50,
+            compression: 1000,
+        });
+        for i in 1..=10_000 {
+            coarse.insert(i as f64);
+            fine.insert(i as f64);
+        }
+
+        let (coarse_est, coarse_err) = coarse.quantile_with_error(0.5);
+        assert_eq!(coarse_est, coarse.quantile(0.5));
+        let (fine_est, fine_err) = fine.quantile_with_error(0.5);
+        assert_eq!(fine_est, fine.quantile(0.5));
+
+        assert!(coarse_err >= 0.0 && fine_err >= 0.0);
+        assert!(fine_err <= coarse_err, "fine {fine_err} should not exceed coarse {coarse_err}");
+    }
+
+    #[test]
+    fn quantile_with_error_is_nan_on_empty_digest() {
+        let state = TDigestQuantileState::new(0);
+        let (est, err) = state.quantile_with_error(0.5);
+        assert!(est.is_nan());
+        assert!(err.is_nan());
+    }
+
+    #[test]
+    fn monotonic_clamps_a_dip_up_to_the_preceding_value() {
+        // A pathological grid where the p99-slot value dips below the
+        // p95-slot value, the artifact this whole mechanism guards against.
+        let grid = [1.0, 5.0, 10.0, 9.5, 12.0];
+        assert_eq!(monotonic(&grid), vec![1.0, 5.0, 10.0, 10.0, 12.0]);
+    }
+
+    #[test]
+    fn monotonic_is_a_no_op_on_an_already_sorted_grid() {
+        let grid = [1.0, 2.0, 3.0, 4.0];
+        assert_eq!(monotonic(&grid), grid.to_vec());
+    }
+
+    #[test]
+    fn monotonic_treats_a_nan_as_the_running_floor() {
+        let grid = [1.0, 2.0, f64::NAN, 3.0];
+        assert_eq!(monotonic(&grid), vec![1.0, 2.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn monotonic_leaves_a_leading_nan_unclamped() {
+        let grid = [f64::NAN, 1.0, 2.0];
+        let result = monotonic(&grid);
+        assert!(result[0].is_nan());
+        assert_eq!(&result[1..], &[1.0, 2.0]);
+    }
+
+    #[test]
+    fn quantile_many_matches_plain_quantile_on_a_well_behaved_digest() {
+        let mut state = TDigestQuantileState::new(0);
+        for i in 1..=10_000 {
+            state.insert(i as f64);
+        }
+        let qs = [0.5, 0.9, 0.95, 0.99, 0.999];
+        let many = state.quantile_many(&qs);
+        for (i, &q) in qs.iter().enumerate() {
+            assert_eq!(many[i], state.quantile(q));
+        }
+    }
+
+    #[test]
+    fn quantile_many_is_always_non_decreasing() {
+        let mut state = TDigestQuantileState::new(0);
+        for i in 1..=5_000 {
+            state.insert((i as f64).sqrt());
+        }
+        let qs = [0.01, 0.1, 0.25, 0.5, 0.75, 0.9, 0.95, 0.99, 0.999, 0.9999];
+        let many = state.quantile_many(&qs);
+        for pair in many.windows(2) {
+            assert!(pair[0] <= pair[1], "{:?} is not non-decreasing", many);
+        }
+    }
+
+    #[test]
+    fn cdf_points_spans_zero_to_one_and_matches_quantile() {
+        let mut state = TDigestQuantileState::new(0);
+        for i in 1..=10_000 {
+            state.insert(i as f64);
+        }
+        let points = state.cdf_points(5);
+        let probs: Vec<f64> = points.iter().map(|&(_, p)| p).collect();
+        assert_eq!(probs, vec![0.0, 0.25, 0.5, 0.75, 1.0]);
+        assert_eq!(points[2].0, state.quantile(0.5));
+    }
+
+    #[test]
+    fn cdf_points_is_empty_on_empty_digest_or_too_few_points() {
+        let state = TDigestQuantileState::new(0);
+        assert!(state.cdf_points(10).is_empty());
+
+        let mut nonempty = TDigestQuantileState::new(0);
+        nonempty.insert(1.0);
+        assert!(nonempty.cdf_points(1).is_empty());
+    }
+
+    #[test]
+    fn merge_many_matches_a_sequential_left_fold() {
+        let chunks: Vec<TDigestQuantileState> = (0..10)
+            .map(|c| {
+                let mut state = TDigestQuantileState::new(0);
+                for i in 1..=1_000 {
+                    state.insert((c * 1_000 + i) as f64);
+                }
+                state
+            })
+            .collect();
+
+        let mut folded = TDigestQuantileState::new(0);
+        for chunk in &chunks {
+            folded.merge_state(chunk);
+        }
+
+        let tree = merge_many(chunks);
+        assert_eq!(tree.count(), folded.count());
+        for &q in &[0.5, 0.9, 0.99] {
+            let (a, b) = (tree.quantile(q), folded.quantile(q));
+            assert!((a - b).abs() < 150.0, "q={q} tree {a} vs folded {b}");
+        }
+    }
+
+    #[test]
+    fn merge_many_is_empty_for_no_states() {
+        let merged = merge_many(Vec::new());
+        assert!(merged.is_empty());
+        assert!(merged.quantile(0.5).is_nan());
+    }
+
+    #[test]
+    fn merge_many_flushes_unflushed_buffers() {
+        let mut a = TDigestQuantileState::new(0);
+        let mut b = TDigestQuantileState::new(0);
+        for i in 1..=10 {
+            a.insert(i as f64);
+            b.insert((i + 10) as f64);
+        }
+        // Neither state has crossed its buffer_capacity, so both digests are
+        // still `None` going into merge_many.
+        let merged = merge_many(vec![a, b]);
+        assert_eq!(merged.count(), 20);
+        let p100 = merged.quantile(1.0);
+        assert!((p100 - 20.0).abs() < 1.0, "p100 was {p100}");
+    }
+
+    #[test]
+    fn merge_state_handles_empty_and_buffer_only_sides() {
+        let mut both_empty = TDigestQuantileState::new(0);
+        both_empty.merge_state(&TDigestQuantileState::new(0));
+        assert_eq!(both_empty.count(), 0);
+        assert!(both_empty.quantile(0.5).is_nan());
+
+        // `other` never crosses the flush threshold, so its digest is `None`
+        // and only its buffer is non-empty.
+        let mut with_buffer_only = TDigestQuantileState::new(10);
+        let mut unflushed = TDigestQuantileState::new(10);
+        for i in 1..=10 {
+            with_buffer_only.insert(i as f64);
+            unflushed.insert((i + 10) as f64);
+        }
+        with_buffer_only.merge_state(&unflushed);
+        assert_eq!(with_buffer_only.count(), 20);
+        let p100 = with_buffer_only.quantile(1.0);
+        assert!((p100 - 20.0).abs() < 1.0, "p100 was {p100}");
+    }
 }
@@ -0,0 +1,285 @@
+//! Zhang–Wang fixed-size epsilon summary ("A Fast Algorithm for Approximate
+//! Quantiles in High Speed Data Streams").
+//!
+//! The summary answers any rank query within `eps*N` of the truth. It is a
+//! hierarchy of fully-compressed, sorted summaries of geometrically increasing
+//! weight: level `l` holds at most `ceil(1/eps)` elements, each standing for
+//! `2^l` input values. A filled level is compressed (halved) and merged into
+//! the level above, cascading upward. Two hierarchies can be unioned
+//! level-by-level with [`ZwQuantileState::merge`], so per-shard states built on
+//! worker threads combine losslessly before a global query.
+
+use std::cmp::Ordering;
+
+use crate::estimator::QuantileEstimator;
+
+/// One summary element: a sampled `value` together with the minimum and maximum
+/// rank it could occupy within its summary.
+#[derive(Debug, Clone)]
+struct Element {
+    value: f64,
+    rmin: usize,
+    rmax: usize,
+}
+
+impl Element {
+    /// Number of input values this element stands for.
+    fn weight(&self) -> usize {
+        self.rmax - self.rmin + 1
+    }
+}
+
+#[derive(Debug)]
+pub struct ZwQuantileState {
+    capacity: usize,
+    /// Level `0` is the fully-compressed buffer of the smallest weight.
+    levels: Vec<Vec<Element>>,
+    buffer: Vec<f64>,
+    count: usize,
+}
+
+impl ZwQuantileState {
+    pub fn new(epsilon: f64) -> Self {
+        let capacity = ((1.0 / epsilon).ceil() as usize).max(1);
+        Self {
+            capacity,
+            levels: Vec::new(),
+            buffer: Vec::with_capacity(capacity),
+            count: 0,
+        }
+    }
+
+    pub fn update(&mut self, x: f64) {
+        self.buffer.push(x);
+        self.count += 1;
+        if self.buffer.len() >= self.capacity {
+            self.seal_buffer();
+        }
+    }
+
+    /// Turn the raw buffer into an exact level-0 summary and fold it into the
+    /// hierarchy.
+    fn seal_buffer(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+        let mut raw = std::mem::replace(&mut self.buffer, Vec::with_capacity(self.capacity));
+        raw.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        let block = raw
+            .into_iter()
+            .enumerate()
+            .map(|(i, value)| Element {
+                value,
+                rmin: i,
+                rmax: i,
+            })
+            .collect();
+        self.add_block(0, block);
+    }
+
+    /// Insert a compressed block at `level`, cascading a compression upward
+    /// whenever a level would overflow its capacity.
+    fn add_block(&mut self, level: usize, block: Vec<Element>) {
+        while self.levels.len() <= level {
+            self.levels.push(Vec::new());
+        }
+        if self.levels[level].is_empty() {
+            self.levels[level] = block;
+            return;
+        }
+        let existing = std::mem::take(&mut self.levels[level]);
+        let merged = merge_sorted(existing, block);
+        let promoted = halve(merged);
+        self.add_block(level + 1, promoted);
+    }
+
+    /// Union another hierarchy into this one, level by level.
+    pub fn merge(&mut self, other: &ZwQuantileState) {
+        for level in 0..other.levels.len() {
+            if !other.levels[level].is_empty() {
+                self.add_block(level, other.levels[level].clone());
+            }
+        }
+        for &x in &other.buffer {
+            self.update(x);
+        }
+        self.count += other.count - other.buffer.len();
+    }
+
+    pub fn query(&self, q: f64) -> f64 {
+        let combined = self.collect_global();
+        if combined.is_empty() {
+            return f64::NAN;
+        }
+        let q = q.clamp(0.0, 1.0);
+        let target = q * self.count as f64;
+
+        let mut best = &combined[0];
+        let mut best_err = f64::INFINITY;
+        for e in &combined {
+            let err = if (e.rmin as f64) <= target && target <= (e.rmax as f64) {
+                0.0
+            } else if target < e.rmin as f64 {
+                e.rmin as f64 - target
+            } else {
+                target - e.rmax as f64
+            };
+            if err < best_err {
+                best_err = err;
+                best = e;
+            }
+        }
+        best.value
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Flatten every level plus the live buffer into a single sorted summary
+    /// whose rank bounds are cumulative over the whole stream.
+    fn collect_global(&self) -> Vec<Element> {
+        let mut all: Vec<Element> = Vec::new();
+        for level in &self.levels {
+            all.extend(level.iter().cloned());
+        }
+        for &x in &self.buffer {
+            all.push(Element {
+                value: x,
+                rmin: 0,
+                rmax: 0,
+            });
+        }
+        all.sort_by(|a, b| a.value.partial_cmp(&b.value).unwrap_or(Ordering::Equal));
+        reindex(&mut all);
+        all
+    }
+}
+
+impl QuantileEstimator for ZwQuantileState {
+    fn insert(&mut self, x: f64) {
+        self.update(x);
+    }
+
+    fn quantile(&self, q: f64) -> f64 {
+        self.query(q)
+    }
+
+    fn count(&self) -> usize {
+        self.count
+    }
+
+    fn merge(&mut self, other: &Self) {
+        ZwQuantileState::merge(self, other);
+    }
+}
+
+/// Merge two value-sorted summaries into one, preserving per-element weight.
+fn merge_sorted(a: Vec<Element>, b: Vec<Element>) -> Vec<Element> {
+    let mut out = Vec::with_capacity(a.len() + b.len());
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i].value <= b[j].value {
+            out.push(a[i].clone());
+            i += 1;
+        } else {
+            out.push(b[j].clone());
+            j += 1;
+        }
+    }
+    out.extend_from_slice(&a[i..]);
+    out.extend_from_slice(&b[j..]);
+    reindex(&mut out);
+    out
+}
+
+/// Halve a summary by dropping every other element; each surviving element
+/// absorbs the weight of the dropped neighbour, widening its rank bounds.
+fn halve(block: Vec<Element>) -> Vec<Element> {
+    let mut out: Vec<Element> = Vec::with_capacity(block.len().div_ceil(2));
+    let mut carry = 0usize;
+    let mut tail: Option<Element> = None;
+    for (i, e) in block.into_iter().enumerate() {
+        if i % 2 == 0 {
+            // Dropped element: hand its weight to the survivor that follows.
+            carry = e.weight();
+            tail = Some(e);
+        } else {
+            out.push(Element {
+                value: e.value,
+                rmin: 0,
+                rmax: e.weight() + carry - 1,
+            });
+            carry = 0;
+            tail = None;
+        }
+    }
+    // An odd-length block ends on a dropped element; keep it as a survivor so
+    // the maximum is never lost.
+    if let Some(last) = tail {
+        out.push(Element {
+            value: last.value,
+            rmin: 0,
+            rmax: last.weight() - 1,
+        });
+    }
+    reindex(&mut out);
+    out
+}
+
+/// Recompute cumulative `rmin`/`rmax` from each element's weight.
+fn reindex(block: &mut [Element]) {
+    let mut acc = 0usize;
+    for e in block.iter_mut() {
+        let w = e.weight();
+        e.rmin = acc;
+        e.rmax = acc + w - 1;
+        acc += w;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_within_epsilon() {
+        let eps = 0.01;
+        let mut state = ZwQuantileState::new(eps);
+        for i in 1..=10_000 {
+            state.update(i as f64);
+        }
+        assert_eq!(state.count(), 10_000);
+        let p50 = state.query(0.5);
+        assert!((p50 - 5_000.0).abs() <= eps * 10_000.0, "p50 was {p50}");
+    }
+
+    #[test]
+    fn merge_is_lossless_in_count() {
+        let mut left = ZwQuantileState::new(0.01);
+        let mut right = ZwQuantileState::new(0.01);
+        for i in 1..=5_000 {
+            left.update(i as f64);
+            right.update((i + 5_000) as f64);
+        }
+        left.merge(&right);
+        assert_eq!(left.count(), 10_000);
+        let p99 = left.query(0.99);
+        assert!((p99 - 9_900.0).abs() <= 0.01 * 10_000.0, "p99 was {p99}");
+    }
+
+    #[test]
+    fn merge_unequal_depth_hierarchies() {
+        // `other` cascades to a deep level while its lower levels are empty;
+        // merging it into a shorter accumulator must not panic.
+        let mut acc = ZwQuantileState::new(0.01);
+        let mut other = ZwQuantileState::new(0.01);
+        for i in 1..=400 {
+            other.update(i as f64);
+        }
+        acc.merge(&other);
+        assert_eq!(acc.count(), 400);
+        let p50 = acc.query(0.5);
+        assert!((p50 - 200.0).abs() <= 0.01 * 400.0 + 1.0, "p50 was {p50}");
+    }
+}
@@ -1,76 +1,3636 @@
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::hash::Hash;
 
-#[derive(Debug, Clone)]
+use serde::{Deserialize, Serialize};
+
+use crate::quantile_brute::exact_quantile;
+
+/// How a quantile is resolved when the fractional rank `h = (n-1)*q` falls
+/// between the two bracketing samples `data[lo]` and `data[hi]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuantileInterpolation {
+    /// Interpolate linearly between the bracketing samples (the default).
+    Linear,
+    /// Take the lower bracketing sample, `data[lo]`.
+    Lower,
+    /// Take the higher bracketing sample, `data[hi]`.
+    Higher,
+    /// Take whichever bracketing sample is closer, breaking ties towards `lo`.
+    Nearest,
+    /// Take the unweighted mean of the two bracketing samples.
+    Midpoint,
+}
+
+/// Alias for [`QuantileInterpolation`] for callers that know this concept by
+/// its more generic name.
+pub type Interpolation = QuantileInterpolation;
+
+impl QuantileInterpolation {
+    /// Combine the two bracketing samples given the fractional weight
+    /// `w = h - lo` of the higher sample.
+    pub(crate) fn apply(self, lo_val: f64, hi_val: f64, w: f64) -> f64 {
+        match self {
+            QuantileInterpolation::Linear => lo_val + (hi_val - lo_val) * w,
+            QuantileInterpolation::Lower => lo_val,
+            QuantileInterpolation::Higher => hi_val,
+            QuantileInterpolation::Nearest => {
+                if w > 0.5 {
+                    hi_val
+                } else {
+                    lo_val
+                }
+            }
+            QuantileInterpolation::Midpoint => (lo_val + hi_val) / 2.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Statistics {
+    #[serde(with = "nan_as_null")]
     pub avg: f64,
+    /// The unrounded total the samples summed to, i.e. `avg * cnt` before
+    /// `avg` was rounded to 2 decimals. `avg` alone loses the precision
+    /// needed to correctly recombine means across groups; downstream code
+    /// computing an exact count-weighted mean over several `Statistics`
+    /// should use `(sum_a + sum_b) / (cnt_a + cnt_b)` rather than averaging
+    /// the already-rounded `avg` fields. `0.0` on empty data. `NaN` on a
+    /// backend that never retained a true sum in the first place (an
+    /// estimator or a bucketed histogram), same as `avg` on those.
+    #[serde(with = "nan_as_null")]
+    pub sum: f64,
+    #[serde(with = "nan_as_null")]
+    pub p1: f64,
+    #[serde(with = "nan_as_null")]
+    pub p5: f64,
+    #[serde(with = "nan_as_null")]
     pub p10: f64,
+    #[serde(with = "nan_as_null")]
+    pub p25: f64,
+    #[serde(with = "nan_as_null")]
     pub p30: f64,
+    #[serde(with = "nan_as_null")]
     pub p50: f64,
+    #[serde(with = "nan_as_null")]
+    pub p75: f64,
+    #[serde(with = "nan_as_null")]
     pub p80: f64,
+    #[serde(with = "nan_as_null")]
     pub p90: f64,
+    #[serde(with = "nan_as_null")]
     pub p95: f64,
+    #[serde(with = "nan_as_null")]
     pub p99: f64,
+    #[serde(with = "nan_as_null")]
     pub p999: f64,
+    /// The 99.99th percentile. For small `cnt` this collapses to
+    /// [`max`](Self::max) — the fractional rank rounds up to the last
+    /// sample well before `cnt` reaches the tens of thousands needed to
+    /// resolve a distinct extreme tail point.
+    #[serde(with = "nan_as_null")]
+    pub p9999: f64,
+    #[serde(with = "nan_as_null")]
+    pub min: f64,
+    #[serde(with = "nan_as_null")]
+    pub max: f64,
+    #[serde(with = "nan_as_null")]
+    pub stddev: f64,
+    #[serde(with = "nan_as_null")]
+    pub variance: f64,
+    pub cnt: usize,
+}
+
+impl Statistics {
+    /// Interpolate a quantile that isn't one of the stored percentiles (e.g.
+    /// p97) from the nearest two that are. This is an **approximation
+    /// derived from the stored percentiles**, not the raw data — two
+    /// different distributions can share the same p95/p99 but disagree on
+    /// p97, so don't mistake this for an exact recomputation. `q` is clamped
+    /// to `[0.0, 1.0]`, returning `min`/`max` at the extremes.
+    pub fn quantile_interp(&self, q: f64) -> f64 {
+        let q = q.clamp(0.0, 1.0);
+        let grid = [
+            (0.0, self.min),
+            (0.01, self.p1),
+            (0.05, self.p5),
+            (0.1, self.p10),
+            (0.25, self.p25),
+            (0.3, self.p30),
+            (0.5, self.p50),
+            (0.75, self.p75),
+            (0.8, self.p80),
+            (0.9, self.p90),
+            (0.95, self.p95),
+            (0.99, self.p99),
+            (0.999, self.p999),
+            (0.9999, self.p9999),
+            (1.0, self.max),
+        ];
+        if q <= grid[0].0 {
+            return grid[0].1;
+        }
+        if q >= grid[grid.len() - 1].0 {
+            return grid[grid.len() - 1].1;
+        }
+        for i in 1..grid.len() {
+            let (hi_q, hi_v) = grid[i];
+            if q <= hi_q {
+                let (lo_q, lo_v) = grid[i - 1];
+                let w = (q - lo_q) / (hi_q - lo_q);
+                return lo_v + (hi_v - lo_v) * w;
+            }
+        }
+        grid[grid.len() - 1].1
+    }
+
+    /// Parse an SLA-doc-style percentile label — `"p50"`, `"p99.9"`,
+    /// `"median"`, `"avg"`/`"mean"`, `"min"`, `"max"` — into the value it
+    /// refers to. Case-insensitive. A `"p<number>"` label whose fraction
+    /// lines up with a stored field (`"p99.9"` -> `0.999` -> `p999`) reads
+    /// that field directly rather than re-deriving it through
+    /// [`quantile_interp`](Self::quantile_interp); a percentile the struct
+    /// doesn't literally store (`"p97"`) falls back to interpolating it.
+    /// Written for config files and CLI args, where writing the fraction by
+    /// hand (`0.9999`) is one dropped nine away from silently querying the
+    /// wrong percentile. `Err(StatError::InvalidQuantile)` on anything that
+    /// doesn't parse as a known label or whose `p`-number falls outside
+    /// `[0, 100]`.
+    pub fn try_quantile_from_label(&self, label: &str) -> Result<f64, StatError> {
+        let label = label.trim().to_ascii_lowercase();
+        match label.as_str() {
+            "min" => return Ok(self.min),
+            "max" => return Ok(self.max),
+            "avg" | "mean" => return Ok(self.avg),
+            "median" => return Ok(self.p50),
+            _ => {}
+        }
+
+        let pct_str = label.strip_prefix('p').ok_or(StatError::InvalidQuantile)?;
+        let pct: f64 = pct_str.parse().map_err(|_| StatError::InvalidQuantile)?;
+        if !(0.0..=100.0).contains(&pct) {
+            return Err(StatError::InvalidQuantile);
+        }
+        let q = pct / 100.0;
+
+        const KNOWN: [(f64, fn(&Statistics) -> f64); 13] = [
+            (0.01, |s| s.p1),
+            (0.05, |s| s.p5),
+            (0.1, |s| s.p10),
+            (0.25, |s| s.p25),
+            (0.3, |s| s.p30),
+            (0.5, |s| s.p50),
+            (0.75, |s| s.p75),
+            (0.8, |s| s.p80),
+            (0.9, |s| s.p90),
+            (0.95, |s| s.p95),
+            (0.99, |s| s.p99),
+            (0.999, |s| s.p999),
+            (0.9999, |s| s.p9999),
+        ];
+        for (known_q, field) in KNOWN {
+            if (q - known_q).abs() < 1e-9 {
+                return Ok(field(self));
+            }
+        }
+        Ok(self.quantile_interp(q))
+    }
+
+    /// Every stored percentile as `(name, value)` pairs in ascending rank
+    /// order, for downstream code that wants to loop over "all the
+    /// percentiles" generically (rendering a table, diffing two `Statistics`
+    /// field by field) instead of naming each field by hand. `avg`/`min`/
+    /// `max`/`stddev`/`variance`/`cnt` are not percentiles and aren't
+    /// included; use the struct fields directly for those.
+    pub fn percentiles(&self) -> impl Iterator<Item = (&'static str, f64)> {
+        [
+            ("p1", self.p1),
+            ("p5", self.p5),
+            ("p10", self.p10),
+            ("p25", self.p25),
+            ("p30", self.p30),
+            ("p50", self.p50),
+            ("p75", self.p75),
+            ("p80", self.p80),
+            ("p90", self.p90),
+            ("p95", self.p95),
+            ("p99", self.p99),
+            ("p999", self.p999),
+            ("p9999", self.p9999),
+        ]
+        .into_iter()
+    }
+
+    /// [`percentiles`](Self::percentiles) collected into a `BTreeMap` keyed by
+    /// name, for callers that want lookups or a stable serialized order
+    /// rather than an iteration order.
+    pub fn percentile_map(&self) -> BTreeMap<String, f64> {
+        self.percentiles().map(|(name, value)| (name.to_string(), value)).collect()
+    }
+
+    /// `hi / lo` over the stored percentiles, `NaN` rather than `inf` if
+    /// `lo` is zero or NaN. The building block for
+    /// [`tail_ratio_99`](Self::tail_ratio_99) and
+    /// [`tail_ratio_999`](Self::tail_ratio_999), exposed directly for callers
+    /// who want a different pair (e.g. `p999 / p95`).
+    pub fn ratio(&self, hi: f64, lo: f64) -> f64 {
+        if lo.is_nan() || lo == 0.0 {
+            f64::NAN
+        } else {
+            hi / lo
+        }
+    }
+
+    /// How much the p99 tail blows up relative to the median: `p99 / p50`.
+    pub fn tail_ratio_99(&self) -> f64 {
+        self.ratio(self.p99, self.p50)
+    }
+
+    /// How much the p999 tail blows up relative to the median: `p999 / p50`.
+    pub fn tail_ratio_999(&self) -> f64 {
+        self.ratio(self.p999, self.p50)
+    }
+
+    /// `stddev / avg`: a scale-free dispersion metric for comparing runs
+    /// with very different mean latencies. `NaN` rather than `inf` if `avg`
+    /// is zero or NaN.
+    pub fn coefficient_of_variation(&self) -> f64 {
+        if self.avg.is_nan() || self.avg == 0.0 {
+            f64::NAN
+        } else {
+            self.stddev / self.avg
+        }
+    }
+
+    /// [`coefficient_of_variation`](Self::coefficient_of_variation) as a
+    /// percentage ("relative standard deviation").
+    pub fn rsd_pct(&self) -> f64 {
+        self.coefficient_of_variation() * 100.0
+    }
+
+    /// Interquartile range, `p75 - p25`: a robust dispersion measure that
+    /// (unlike `stddev`) isn't dragged around by the tail.
+    pub fn iqr(&self) -> f64 {
+        self.p75 - self.p25
+    }
+}
+
+/// Per-field deltas between two [`Statistics`] snapshots, as produced by
+/// [`Statistics::diff`]. Each pair is `(current - baseline, percentage
+/// change)`; the percentage is `None` when `baseline` is `NaN` or `0.0`,
+/// since "regressed by what percent of zero" has no meaningful answer.
+#[derive(Debug, Clone)]
+pub struct StatisticsDiff {
+    pub avg: (f64, Option<f64>),
+    pub p50: (f64, Option<f64>),
+    pub p90: (f64, Option<f64>),
+    pub p95: (f64, Option<f64>),
+    pub p99: (f64, Option<f64>),
+    pub p999: (f64, Option<f64>),
+    pub max: (f64, Option<f64>),
+}
+
+/// Compute `(current - baseline, pct_change)` for one field pair.
+fn diff_field(current: f64, baseline: f64) -> (f64, Option<f64>) {
+    let delta = current - baseline;
+    let pct = if baseline.is_nan() || baseline == 0.0 {
+        None
+    } else {
+        Some(delta / baseline * 100.0)
+    };
+    (delta, pct)
+}
+
+impl Statistics {
+    /// Compare `self` (the current run) against `baseline` (a prior run),
+    /// field by field.
+    pub fn diff(&self, baseline: &Statistics) -> StatisticsDiff {
+        StatisticsDiff {
+            avg: diff_field(self.avg, baseline.avg),
+            p50: diff_field(self.p50, baseline.p50),
+            p90: diff_field(self.p90, baseline.p90),
+            p95: diff_field(self.p95, baseline.p95),
+            p99: diff_field(self.p99, baseline.p99),
+            p999: diff_field(self.p999, baseline.p999),
+            max: diff_field(self.max, baseline.max),
+        }
+    }
+
+    /// Names of the latency fields that regressed (got worse, i.e. grew) by
+    /// more than `threshold_pct` percent relative to `baseline`. A field
+    /// whose baseline is `NaN`/`0.0` never reports a regression, since there
+    /// is no percentage change to compare against the threshold.
+    pub fn regressed(&self, baseline: &Statistics, threshold_pct: f64) -> Vec<String> {
+        let d = self.diff(baseline);
+        let fields: [(&str, Option<f64>); 7] = [
+            ("avg", d.avg.1),
+            ("p50", d.p50.1),
+            ("p90", d.p90.1),
+            ("p95", d.p95.1),
+            ("p99", d.p99.1),
+            ("p999", d.p999.1),
+            ("max", d.max.1),
+        ];
+        fields
+            .into_iter()
+            .filter(|(_, pct)| pct.is_some_and(|p| p > threshold_pct))
+            .map(|(name, _)| name.to_string())
+            .collect()
+    }
+}
+
+impl Statistics {
+    /// Field-by-field approximate equality with a relative tolerance,
+    /// treating `NaN == NaN` as equal (so two empty-data `Statistics`
+    /// compare equal) instead of the usual IEEE-754 `NaN != NaN` — this is
+    /// what an integration test comparing a computed `Statistics` against an
+    /// expected one actually wants. `cnt` is compared exactly since it isn't
+    /// a float.
+    pub fn approx_eq(&self, other: &Statistics, rel_tol: f64) -> bool {
+        let close = |a: f64, b: f64| -> bool {
+            if a.is_nan() && b.is_nan() {
+                return true;
+            }
+            if a.is_nan() || b.is_nan() {
+                return false;
+            }
+            let scale = a.abs().max(b.abs()).max(1.0);
+            (a - b).abs() <= rel_tol * scale
+        };
+        self.cnt == other.cnt
+            && close(self.avg, other.avg)
+            && close(self.sum, other.sum)
+            && close(self.p1, other.p1)
+            && close(self.p5, other.p5)
+            && close(self.p10, other.p10)
+            && close(self.p25, other.p25)
+            && close(self.p30, other.p30)
+            && close(self.p50, other.p50)
+            && close(self.p75, other.p75)
+            && close(self.p80, other.p80)
+            && close(self.p90, other.p90)
+            && close(self.p95, other.p95)
+            && close(self.p99, other.p99)
+            && close(self.p999, other.p999)
+            && close(self.p9999, other.p9999)
+            && close(self.min, other.min)
+            && close(self.max, other.max)
+            && close(self.stddev, other.stddev)
+            && close(self.variance, other.variance)
+    }
+}
+
+/// Assert two [`Statistics`] are equal within a relative tolerance via
+/// [`Statistics::approx_eq`], panicking with both values (and the tolerance)
+/// on failure like the standard `assert_eq!`.
+#[macro_export]
+macro_rules! assert_stats_approx_eq {
+    ($left:expr, $right:expr, $rel_tol:expr) => {
+        match (&$left, &$right, $rel_tol) {
+            (left_val, right_val, tol) => {
+                if !left_val.approx_eq(right_val, tol) {
+                    panic!(
+                        "assertion failed: `(left ~= right)`\n  left: `{:?}`\n right: `{:?}`\n   tol: `{:?}`",
+                        left_val, right_val, tol
+                    );
+                }
+            }
+        }
+    };
+}
+
+/// Per-field allowed regression, as a percentage increase over baseline, for
+/// [`regression_report`]. `None` on a field means that field is never
+/// flagged regardless of how much it grew — for fields a caller doesn't
+/// track (or intentionally ignores, e.g. `max` being noisy on small samples).
+/// Typically tighter on `p50` (a steady, high-traffic percentile where small
+/// regressions matter) and looser on `p999` (a handful of samples away from
+/// pure noise).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RegressionThresholds {
+    pub avg: Option<f64>,
+    pub p50: Option<f64>,
+    pub p90: Option<f64>,
+    pub p95: Option<f64>,
+    pub p99: Option<f64>,
+    pub p999: Option<f64>,
+    pub max: Option<f64>,
+}
+
+/// The verdict [`regression_report`] produces: an overall pass/fail, the raw
+/// per-field deltas it was computed from, and the names of the fields that
+/// tripped their threshold. `to_string()` (via `Display`) renders this as the
+/// plain-text block a CI job would print.
+#[derive(Debug, Clone)]
+pub struct RegressionReport {
+    pub passed: bool,
+    pub diff: StatisticsDiff,
+    pub regressions: Vec<String>,
+}
+
+impl fmt::Display for RegressionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "regression check: {}", if self.passed { "PASS" } else { "FAIL" })?;
+        let fields: [(&str, (f64, Option<f64>)); 7] = [
+            ("avg", self.diff.avg),
+            ("p50", self.diff.p50),
+            ("p90", self.diff.p90),
+            ("p95", self.diff.p95),
+            ("p99", self.diff.p99),
+            ("p999", self.diff.p999),
+            ("max", self.diff.max),
+        ];
+        for (name, (delta, pct)) in fields {
+            let flagged = if self.regressions.iter().any(|r| r == name) { " [REGRESSED]" } else { "" };
+            match pct {
+                Some(pct) => writeln!(f, "  {name}: {delta:+.3} ({pct:+.2}%){flagged}")?,
+                None => writeln!(f, "  {name}: {delta:+.3} (n/a%){flagged}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compare `current` against `baseline` field by field, flagging any field
+/// whose percentage increase exceeds its `thresholds` entry, and produce the
+/// combined pass/fail verdict a CI perf gate would print (and, on failure,
+/// parse the `regressions` list from to decide which check to blame). A field
+/// with no threshold set, or whose baseline is `NaN`/`0.0` (so it has no
+/// percentage change to compare), never fails the gate on its own.
+pub fn regression_report(
+    baseline: &Statistics,
+    current: &Statistics,
+    thresholds: &RegressionThresholds,
+) -> RegressionReport {
+    let diff = current.diff(baseline);
+    let fields: [(&str, Option<f64>, Option<f64>); 7] = [
+        ("avg", diff.avg.1, thresholds.avg),
+        ("p50", diff.p50.1, thresholds.p50),
+        ("p90", diff.p90.1, thresholds.p90),
+        ("p95", diff.p95.1, thresholds.p95),
+        ("p99", diff.p99.1, thresholds.p99),
+        ("p999", diff.p999.1, thresholds.p999),
+        ("max", diff.max.1, thresholds.max),
+    ];
+    let regressions: Vec<String> = fields
+        .into_iter()
+        .filter(|(_, pct, threshold)| {
+            pct.zip(*threshold).is_some_and(|(pct, threshold)| pct > threshold)
+        })
+        .map(|(name, _, _)| name.to_string())
+        .collect();
+
+    RegressionReport {
+        passed: regressions.is_empty(),
+        diff,
+        regressions,
+    }
+}
+
+/// Best-effort aggregate of already-computed [`Statistics`], for when only
+/// each node's percentiles and count survive, not the raw samples. `cnt` and
+/// `sum` are the exact totals across parts, `avg` is re-derived from that
+/// exact `sum` (rather than weight-averaging the parts' already-rounded
+/// `avg` fields), and `min`/`max` are the true min/max across parts. Every
+/// percentile field
+/// (`p10`..`p999`) and `stddev`/`variance`, however, is only the
+/// count-weighted average of that field across parts — **not** a recomputation
+/// over the union of the underlying data, so it can disagree with the true
+/// percentile whenever the parts' distributions differ in shape. Parts with
+/// `cnt == 0` are ignored rather than pulling every field towards `NaN`.
+pub fn merge_statistics(parts: &[Statistics]) -> Statistics {
+    let total_cnt: usize = parts.iter().map(|s| s.cnt).sum();
+    if total_cnt == 0 {
+        return statistics_from_sorted(&[]);
+    }
+
+    let weighted = |pick: fn(&Statistics) -> f64| -> f64 {
+        parts
+            .iter()
+            .filter(|s| s.cnt > 0)
+            .map(|s| pick(s) * (s.cnt as f64))
+            .sum::<f64>()
+            / (total_cnt as f64)
+    };
+    let extreme = |pick: fn(&Statistics) -> f64, better: fn(f64, f64) -> bool| -> f64 {
+        parts
+            .iter()
+            .filter(|s| s.cnt > 0)
+            .map(pick)
+            .fold(f64::NAN, |acc, x| if acc.is_nan() || better(x, acc) { x } else { acc })
+    };
+
+    let total_sum: f64 = parts.iter().filter(|s| s.cnt > 0).map(|s| s.sum).sum();
+
+    Statistics {
+        avg: (total_sum / (total_cnt as f64) * 100.0).round() / 100.0,
+        sum: total_sum,
+        p1: weighted(|s| s.p1),
+        p5: weighted(|s| s.p5),
+        p10: weighted(|s| s.p10),
+        p25: weighted(|s| s.p25),
+        p30: weighted(|s| s.p30),
+        p50: weighted(|s| s.p50),
+        p75: weighted(|s| s.p75),
+        p80: weighted(|s| s.p80),
+        p90: weighted(|s| s.p90),
+        p95: weighted(|s| s.p95),
+        p99: weighted(|s| s.p99),
+        p999: weighted(|s| s.p999),
+        p9999: weighted(|s| s.p9999),
+        min: extreme(|s| s.min, |x, acc| x < acc),
+        max: extreme(|s| s.max, |x, acc| x > acc),
+        stddev: weighted(|s| s.stddev),
+        variance: weighted(|s| s.variance),
+        cnt: total_cnt,
+    }
+}
+
+/// Bucket `samples` by key and compute a [`Statistics`] per bucket in one
+/// pass — the RPC-method-level latency table ("`cfx_getBalance`: p99 12ms,
+/// `cfx_sendRawTransaction`: p99 340ms, ...") most of our reports build.
+/// Iteration order of `samples` determines the order values land in each
+/// bucket's `Vec`, but [`statistics_from_vec`] sorts internally so that has
+/// no effect on the result.
+pub fn grouped_statistics<K: Eq + Hash>(
+    samples: impl IntoIterator<Item = (K, f64)>,
+) -> HashMap<K, Statistics> {
+    let mut buckets: HashMap<K, Vec<f64>> = HashMap::new();
+    for (key, value) in samples {
+        buckets.entry(key).or_default().push(value);
+    }
+    buckets
+        .into_iter()
+        .map(|(key, values)| (key, statistics_from_vec(values)))
+        .collect()
+}
+
+/// Combine every group's [`Statistics`] from [`grouped_statistics`] into one
+/// overall figure, the way [`merge_statistics`] combines any other set of
+/// per-shard `Statistics`.
+pub fn overall_statistics<K>(groups: &HashMap<K, Statistics>) -> Statistics {
+    let parts: Vec<Statistics> = groups.values().cloned().collect();
+    merge_statistics(&parts)
+}
+
+/// Minimum number of preceding points required before a point can be judged
+/// a spike, so the rolling mean/stddev in [`detect_spikes`] isn't fit to a
+/// handful of samples.
+const SPIKE_WARMUP: usize = 5;
+
+/// Flag windows in a time series of [`Statistics`] (one per fixed-length
+/// harness window, e.g. every 10 seconds) where `field` jumps more than
+/// `z_threshold` standard deviations above its rolling mean, e.g.
+/// `detect_spikes(&windows, |s| s.p99, 3.0)` to catch the window where p99
+/// degraded. The rolling mean/stddev is computed online over every prior
+/// point (not a fixed-size sliding window), and the first
+/// [`SPIKE_WARMUP`] points are never flagged since there isn't enough
+/// history yet to judge them against. `NaN` field values are skipped
+/// entirely — neither flagged nor folded into the rolling stats.
+pub fn detect_spikes(
+    series: &[Statistics],
+    field: fn(&Statistics) -> f64,
+    z_threshold: f64,
+) -> Vec<usize> {
+    let mut history = OnlineStats::new();
+    let mut flagged = Vec::new();
+    for (i, point) in series.iter().enumerate() {
+        let value = field(point);
+        if value.is_nan() {
+            continue;
+        }
+        if i >= SPIKE_WARMUP {
+            let stddev = history.stddev();
+            // A perfectly flat baseline has a zero stddev, so the usual
+            // z-score is undefined (division by zero) rather than merely
+            // large: any departure from that constant is a spike on its own.
+            let is_spike = if stddev > 0.0 {
+                (value - history.avg()) / stddev > z_threshold
+            } else {
+                value != history.avg()
+            };
+            if is_spike {
+                flagged.push(i);
+            }
+        }
+        history.insert(value);
+    }
+    flagged
+}
+
+/// Coefficient of variation (`stddev / mean`, population) of each named
+/// percentile across `runs`, for telling a real regression apart from
+/// run-to-run noise: if `p99` normally has a CV of 8% across repeated
+/// identical runs, a single run's 5% p99 delta from baseline is well within
+/// that noise, but an 8% delta might not be. Uses the same field names as
+/// [`Statistics::percentiles`]. A `NaN` value in any run for a given
+/// percentile drops that run from that percentile's computation. A
+/// percentile with fewer than 2 surviving values, or a zero mean, reports
+/// `NaN` rather than `0.0`/`inf`.
+pub fn percentile_stability(runs: &[Statistics]) -> HashMap<String, f64> {
+    let mut out = HashMap::new();
+    if runs.is_empty() {
+        return out;
+    }
+    for (name, _) in runs[0].percentiles() {
+        let values: Vec<f64> = runs
+            .iter()
+            .map(|stat| stat.percentiles().find(|(n, _)| *n == name).unwrap().1)
+            .filter(|v| !v.is_nan())
+            .collect();
+        let cv = if values.len() < 2 {
+            f64::NAN
+        } else {
+            let mean = values.iter().sum::<f64>() / (values.len() as f64);
+            if mean == 0.0 {
+                f64::NAN
+            } else {
+                let variance =
+                    values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (values.len() as f64);
+                variance.sqrt() / mean
+            }
+        };
+        out.insert(name.to_string(), cv);
+    }
+    out
+}
+
+/// Render `labels` as a Prometheus label set (`{k="v",...}`), empty string if
+/// `labels` is empty. `extra`, when given, is appended as one more label
+/// (e.g. `quantile="0.99"`) without a leading comma of its own.
+fn prometheus_labels(labels: &[(&str, &str)], extra: Option<String>) -> String {
+    let mut parts: Vec<String> = labels.iter().map(|(k, v)| format!("{k}=\"{v}\"")).collect();
+    parts.extend(extra);
+    if parts.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", parts.join(","))
+    }
+}
+
+/// How many decimal places an output helper renders a float with. `None`
+/// means full `f64` precision (via `f64::to_string`, i.e. the shortest
+/// round-trippable representation), `Some(n)` fixes it to `n` decimals via
+/// `{:.n}`. Threading this through [`to_prometheus`]/[`to_csv_row`]/
+/// [`Statistics`]'s `Display` impl keeps every output helper's precision
+/// configurable from one place instead of a `{x:.2}` hardcoded per function.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FloatFmt {
+    pub decimals: Option<usize>,
+}
+
+impl FloatFmt {
+    /// Full `f64` precision, no rounding — [`to_prometheus`]'s default.
+    pub const FULL: FloatFmt = FloatFmt { decimals: None };
+
+    /// Two decimal places — [`to_csv_row`] and `Statistics`'s `Display`
+    /// impl's default.
+    pub const TWO_DECIMALS: FloatFmt = FloatFmt { decimals: Some(2) };
+
+    pub fn decimals(n: usize) -> FloatFmt {
+        FloatFmt { decimals: Some(n) }
+    }
+
+    fn format(self, x: f64) -> String {
+        match self.decimals {
+            Some(n) => format!("{x:.n$}"),
+            None => x.to_string(),
+        }
+    }
+}
+
+/// Render `stat` in Prometheus text exposition format as a summary: a
+/// `{metric_name}_count`, a `{metric_name}_sum`, and one
+/// `{metric_name}{{quantile="...",...labels}}` gauge line per stored
+/// percentile (including `min`/`max` at `quantile="0"`/`"1"`). Any `NaN`
+/// field (an empty-data `Statistics`) is omitted from its line entirely
+/// rather than emitted as `NaN`, which Prometheus can't parse. Full `f64`
+/// precision; use [`to_prometheus_fmt`] to round.
+pub fn to_prometheus(stat: &Statistics, metric_name: &str, labels: &[(&str, &str)]) -> String {
+    to_prometheus_fmt(stat, metric_name, labels, FloatFmt::FULL)
+}
+
+/// Like [`to_prometheus`] but rendering every value through `fmt`.
+pub fn to_prometheus_fmt(
+    stat: &Statistics,
+    metric_name: &str,
+    labels: &[(&str, &str)],
+    fmt: FloatFmt,
+) -> String {
+    let base_labels = prometheus_labels(labels, None);
+    let mut out = String::new();
+    out.push_str(&format!("{metric_name}_count{base_labels} {}\n", stat.cnt));
+    if !stat.sum.is_nan() {
+        out.push_str(&format!("{metric_name}_sum{base_labels} {}\n", fmt.format(stat.sum)));
+    }
+
+    let quantiles: [(&str, f64); 15] = [
+        ("0", stat.min),
+        ("0.01", stat.p1),
+        ("0.05", stat.p5),
+        ("0.1", stat.p10),
+        ("0.25", stat.p25),
+        ("0.3", stat.p30),
+        ("0.5", stat.p50),
+        ("0.75", stat.p75),
+        ("0.8", stat.p80),
+        ("0.9", stat.p90),
+        ("0.95", stat.p95),
+        ("0.99", stat.p99),
+        ("0.999", stat.p999),
+        ("0.9999", stat.p9999),
+        ("1", stat.max),
+    ];
+    for (q, value) in quantiles {
+        if value.is_nan() {
+            continue;
+        }
+        let line_labels = prometheus_labels(labels, Some(format!("quantile=\"{q}\"")));
+        out.push_str(&format!("{metric_name}{line_labels} {}\n", fmt.format(value)));
+    }
+    out
+}
+
+/// Column names for [`to_csv_row`], in [`Statistics`]'s declaration order, so
+/// the header and a row can never drift apart the way a hand-maintained CSV
+/// writer's copy of the field list can.
+pub fn csv_header() -> String {
+    "avg,p1,p5,p10,p25,p30,p50,p75,p80,p90,p95,p99,p999,p9999,min,max,stddev,variance,cnt".to_string()
+}
+
+/// One CSV row for `stat`, in the same field order as [`csv_header`]. Floats
+/// are formatted to 2 decimal places; `NaN` (an empty-data `Statistics`)
+/// renders as an empty field rather than the literal text `NaN`. Use
+/// [`to_csv_row_fmt`] for a different precision.
+pub fn to_csv_row(stat: &Statistics) -> String {
+    to_csv_row_fmt(stat, FloatFmt::TWO_DECIMALS)
+}
+
+/// Like [`to_csv_row`] but rendering every float through `fmt`.
+pub fn to_csv_row_fmt(stat: &Statistics, fmt: FloatFmt) -> String {
+    let cell = |x: f64| if x.is_nan() { String::new() } else { fmt.format(x) };
+    [
+        cell(stat.avg),
+        cell(stat.p1),
+        cell(stat.p5),
+        cell(stat.p10),
+        cell(stat.p25),
+        cell(stat.p30),
+        cell(stat.p50),
+        cell(stat.p75),
+        cell(stat.p80),
+        cell(stat.p90),
+        cell(stat.p95),
+        cell(stat.p99),
+        cell(stat.p999),
+        cell(stat.p9999),
+        cell(stat.min),
+        cell(stat.max),
+        cell(stat.stddev),
+        cell(stat.variance),
+        stat.cnt.to_string(),
+    ]
+    .join(",")
+}
+
+/// A `timestamp` column plus one column per name in `qs` (any
+/// [`statistics_to_map`] key, e.g. `"p50"`, `"p99"`, `"cnt"`), one row per
+/// `(timestamp, stat)` in `snapshots` — the "percentile vs time" export every
+/// trend dashboard otherwise reassembles from repeated `quantile` calls by
+/// hand. A name in `qs` that isn't a `Statistics` field, or a percentile that
+/// was `NaN` in that snapshot, renders as an empty cell rather than an error.
+pub fn percentile_timeseries_csv(snapshots: &[(f64, Statistics)], qs: &[&str]) -> String {
+    let mut out = String::new();
+    out.push_str("timestamp");
+    for q in qs {
+        out.push(',');
+        out.push_str(q);
+    }
+    out.push('\n');
+    for (timestamp, stat) in snapshots {
+        let map = statistics_to_map(stat);
+        out.push_str(&timestamp.to_string());
+        for q in qs {
+            out.push(',');
+            if let Some(value) = f64_from_stat(&map, q) {
+                out.push_str(&value.to_string());
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+impl Statistics {
+    /// Render the same table [`Display`](fmt::Display) prints, but with
+    /// every float through `fmt` instead of the hardcoded 2 decimals —
+    /// e.g. `FloatFmt::decimals(1)` for a narrower terminal summary.
+    pub fn render(&self, fmt: FloatFmt) -> String {
+        let cell = |x: f64| if x.is_nan() { "n/a".to_string() } else { fmt.format(x) };
+        format!(
+            "cnt:    {}\navg:    {}\nmin:    {}\np1:     {}\np5:     {}\np10:    {}\np25:    {}\np30:    {}\np50:    {}\np75:    {}\np80:    {}\np90:    {}\np95:    {}\np99:    {}\np999:   {}\np9999:  {}\nmax:    {}\nstddev: {}\nvariance: {}",
+            self.cnt,
+            cell(self.avg),
+            cell(self.min),
+            cell(self.p1),
+            cell(self.p5),
+            cell(self.p10),
+            cell(self.p25),
+            cell(self.p30),
+            cell(self.p50),
+            cell(self.p75),
+            cell(self.p80),
+            cell(self.p90),
+            cell(self.p95),
+            cell(self.p99),
+            cell(self.p999),
+            cell(self.p9999),
+            cell(self.max),
+            cell(self.stddev),
+            cell(self.variance),
+        )
+    }
+}
+
+/// A human-readable table for terminal/log output, distinct from the
+/// `Debug` derive: fields are aligned in a fixed column and `NaN` prints as
+/// `n/a` instead of Rust's `NaN` so an empty-data `Statistics` reads cleanly.
+/// Fixed at 2 decimal places; use [`Statistics::render`] for a configurable
+/// precision.
+impl fmt::Display for Statistics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.render(FloatFmt::TWO_DECIMALS))
+    }
+}
+
+/// `f64`'s `Serialize`/`Deserialize` have no notion of JSON `null`, so an
+/// empty-data [`Statistics`] (all fields `NaN`) would otherwise fail to
+/// round-trip. This maps `NaN <-> null` field-by-field via `#[serde(with)]`.
+mod nan_as_null {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        if value.is_nan() {
+            serializer.serialize_none()
+        } else {
+            serializer.serialize_some(value)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        Ok(Option::<f64>::deserialize(deserializer)?.unwrap_or(f64::NAN))
+    }
+}
+
+/// Resolve a single quantile against already-sorted `data`. Shared by
+/// [`statistics_from_sorted_with_interp`] and [`statistics_from_sorted_with`]
+/// so both fixed and caller-chosen percentile sets interpolate identically.
+pub(crate) fn quantile_of_sorted(data: &[f64], q: f64, interp: QuantileInterpolation) -> f64 {
+    if data.is_empty() {
+        return f64::NAN;
+    }
+    if data.len() == 1 {
+        return data[0];
+    }
+    let q = q.clamp(0.0, 1.0);
+    let h = (data.len() - 1) as f64 * q;
+    let lo = h.floor() as usize;
+    let hi = h.ceil() as usize;
+    if lo == hi {
+        return data[lo];
+    }
+    let w = h - (lo as f64);
+    interp.apply(data[lo], data[hi], w)
+}
+
+/// Neumaier-compensated sum: a running-sum pass that also tracks the
+/// rounding error each addition drops, folding it back in at the end.
+/// Substantially more accurate than a naive `data.iter().sum()` on large
+/// datasets or ones spanning several orders of magnitude, where the naive
+/// sum's accumulated error can disagree in the last digit or two across
+/// platforms — exactly the kind of drift that breaks a golden-file test
+/// comparing `avg` byte-for-byte.
+fn neumaier_sum(data: &[f64]) -> f64 {
+    let mut sum = 0.0;
+    let mut correction = 0.0;
+    for &x in data {
+        let t = sum + x;
+        if sum.abs() >= x.abs() {
+            correction += (sum - t) + x;
+        } else {
+            correction += (x - t) + sum;
+        }
+        sum = t;
+    }
+    sum + correction
+}
+
+/// A caller-chosen alternative to [`Statistics`]'s fixed percentile set:
+/// `avg`/`max`/`cnt` plus exactly the quantiles requested, keyed by their
+/// `q` value formatted as a string (e.g. `"0.5"`, `"0.999"`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomStatistics {
+    #[serde(with = "nan_as_null")]
+    pub avg: f64,
+    #[serde(with = "nan_as_null")]
     pub max: f64,
     pub cnt: usize,
+    pub quantiles: HashMap<String, f64>,
+}
+
+/// Like [`statistics_from_sorted`] but computing exactly the requested
+/// `quantiles` instead of the fixed p10/p30/.../p999 set, so callers that
+/// only need p50/p99 (or need p9999 for tail analysis) don't have to fork
+/// the crate to get a different percentile list.
+pub fn statistics_from_sorted_with(data: &[f64], quantiles: &[f64]) -> CustomStatistics {
+    statistics_from_sorted_with_interp_and(data, quantiles, QuantileInterpolation::Linear)
+}
+
+/// [`statistics_from_sorted_with`] with an explicit [`QuantileInterpolation`].
+pub fn statistics_from_sorted_with_interp_and(
+    data: &[f64],
+    quantiles: &[f64],
+    interp: QuantileInterpolation,
+) -> CustomStatistics {
+    if data.is_empty() {
+        return CustomStatistics {
+            avg: f64::NAN,
+            max: f64::NAN,
+            cnt: 0,
+            quantiles: quantiles.iter().map(|q| (q.to_string(), f64::NAN)).collect(),
+        };
+    }
+
+    let cnt = data.len();
+    let sum: f64 = neumaier_sum(data);
+    let avg = (sum / (cnt as f64) * 100.0).round() / 100.0;
+
+    CustomStatistics {
+        avg,
+        max: *data.last().unwrap(),
+        cnt,
+        quantiles: quantiles
+            .iter()
+            .map(|&q| (q.to_string(), quantile_of_sorted(data, q, interp)))
+            .collect(),
+    }
+}
+
+/// Failure modes for the `try_*` functions, which fail loudly instead of
+/// silently returning `NaN` the way their non-`try_` counterparts do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatError {
+    /// `data` had no samples.
+    Empty,
+    /// `q` was outside `[0.0, 1.0]`, checked before any clamping happens —
+    /// clamping a caller bug into a silently-different answer is worse than
+    /// rejecting it.
+    InvalidQuantile,
+    /// `data` contained one or more `NaN` samples, checked by
+    /// [`try_statistics_from_vec_strict`].
+    ContainsNaN,
+    /// A `(value, weight)` pair passed to [`try_weighted_statistics`] had a
+    /// negative weight, which has no sensible meaning for a cumulative-weight
+    /// rank.
+    NegativeWeight,
+    /// `data` passed to [`statistics_from_sorted_checked`] was not sorted in
+    /// non-decreasing order (ignoring NaN), so the `_from_sorted` family's
+    /// precondition doesn't hold.
+    NotSorted,
+}
+
+impl fmt::Display for StatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StatError::Empty => write!(f, "no samples to compute statistics over"),
+            StatError::InvalidQuantile => write!(f, "quantile must be in [0.0, 1.0]"),
+            StatError::ContainsNaN => write!(f, "data contained one or more NaN samples"),
+            StatError::NegativeWeight => write!(f, "weighted sample had a negative weight"),
+            StatError::NotSorted => write!(f, "data passed to a _from_sorted function was not sorted"),
+        }
+    }
+}
+
+/// Whether `data` is sorted in non-decreasing order, ignoring NaN (which
+/// [`quantile_of_sorted`] and friends never dereference past anyway — a NaN
+/// anywhere in `data` already produces `NaN` percentiles regardless of its
+/// position). Shared by [`statistics_from_sorted_with_interp`]'s
+/// `debug_assert!` and [`statistics_from_sorted_checked`]'s release-mode
+/// check.
+fn is_sorted_non_decreasing(data: &[f64]) -> bool {
+    data.windows(2).all(|w| w[0].is_nan() || w[1].is_nan() || w[0] <= w[1])
+}
+
+impl std::error::Error for StatError {}
+
+/// Like [`statistics_from_sorted`] but returning [`StatError::Empty`]
+/// instead of a `Statistics` full of `NaN`s when `data` is empty.
+pub fn try_statistics_from_sorted(data: &[f64]) -> Result<Statistics, StatError> {
+    if data.is_empty() {
+        return Err(StatError::Empty);
+    }
+    Ok(statistics_from_sorted(data))
 }
 
+/// **Precondition: `data` must already be sorted in non-decreasing order**
+/// (ignoring NaN placement). This is trusted, not re-checked, so passing
+/// unsorted data silently produces garbage percentiles rather than an error —
+/// use [`statistics_from_sorted_checked`] if `data`'s provenance isn't
+/// certain, or [`statistics_from_vec`] to sort it yourself first.
 pub fn statistics_from_sorted(data: &[f64]) -> Statistics {
+    statistics_from_sorted_with_interp(data, QuantileInterpolation::Linear)
+}
+
+/// Like [`statistics_from_sorted`] but returning [`StatError::NotSorted`]
+/// instead of silently misinterpolating when `data` isn't actually sorted —
+/// the release-mode counterpart to the `debug_assert!` inside
+/// [`statistics_from_sorted_with_interp`], for callers that can't rely on
+/// debug builds catching the mistake before it reaches production.
+pub fn statistics_from_sorted_checked(data: &[f64]) -> Result<Statistics, StatError> {
+    if !is_sorted_non_decreasing(data) {
+        return Err(StatError::NotSorted);
+    }
+    Ok(statistics_from_sorted(data))
+}
+
+pub fn statistics_from_sorted_with_interp(
+    data: &[f64],
+    interp: QuantileInterpolation,
+) -> Statistics {
+    debug_assert!(
+        is_sorted_non_decreasing(data),
+        "statistics_from_sorted_with_interp received unsorted data; every _from_sorted function \
+         trusts its input is already sorted and will silently return garbage percentiles rather \
+         than an error. Sort it first, or use statistics_from_sorted_checked."
+    );
     if data.is_empty() {
         return Statistics {
             avg: f64::NAN,
+            sum: f64::NAN,
+            p1: f64::NAN,
+            p5: f64::NAN,
             p10: f64::NAN,
+            p25: f64::NAN,
             p30: f64::NAN,
             p50: f64::NAN,
+            p75: f64::NAN,
             p80: f64::NAN,
             p90: f64::NAN,
             p95: f64::NAN,
             p99: f64::NAN,
             p999: f64::NAN,
+            p9999: f64::NAN,
+            min: f64::NAN,
             max: f64::NAN,
+            stddev: f64::NAN,
+            variance: f64::NAN,
             cnt: 0,
         };
     }
 
     let cnt = data.len();
-    let sum: f64 = data.iter().sum();
-    let avg = (sum / (cnt as f64) * 100.0).round() / 100.0;
-    let pick = |q: f64| -> f64 {
-        if cnt == 1 {
-            return data[0];
-        }
-        let q = q.clamp(0.0, 1.0);
-        let h = (cnt - 1) as f64 * q;
-        let lo = h.floor() as usize;
-        let hi = h.ceil() as usize;
-        if lo == hi {
-            return data[lo];
-        }
-        let w = h - (lo as f64);
-        data[lo] + (data[hi] - data[lo]) * w
+    let sum: f64 = neumaier_sum(data);
+    let mean = sum / (cnt as f64);
+    let avg = (mean * 100.0).round() / 100.0;
+    // Two-pass population variance: the second pass over the deviations from
+    // the exact mean avoids the precision loss a single running-sum pass
+    // would accumulate on large datasets with a large mean.
+    let variance = if cnt == 1 {
+        0.0
+    } else {
+        data.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (cnt as f64)
     };
+    let stddev = (variance.sqrt() * 100.0).round() / 100.0;
+    let pick = |q: f64| quantile_of_sorted(data, q, interp);
 
     Statistics {
         avg,
+        sum,
+        p1: pick(0.01),
+        p5: pick(0.05),
         p10: pick(0.1),
+        p25: pick(0.25),
         p30: pick(0.3),
         p50: pick(0.5),
+        p75: pick(0.75),
         p80: pick(0.8),
         p90: pick(0.9),
         p95: pick(0.95),
         p99: pick(0.99),
         p999: pick(0.999),
+        p9999: pick(0.9999),
+        min: data[0],
         max: *data.last().unwrap(),
+        stddev,
+        variance,
         cnt,
     }
 }
 
-pub fn statistics_from_vec(mut data: Vec<f64>) -> Statistics {
-    data.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
-    statistics_from_sorted(&data)
+/// The nearest-rank percentile: the smallest sample such that at least `q`
+/// of the data is `<=` it, i.e. `data[ceil(q * n) - 1]` with the rank
+/// clamped into `[1, n]`. Unlike [`quantile_of_sorted`], which interpolates
+/// between the two bracketing samples, this always returns one of the
+/// actual samples — the definition many SLA documents mean when they write
+/// "p99" ("at least 99% of requests complete within this long").
+fn nearest_rank_of_sorted(data: &[f64], q: f64) -> f64 {
+    if data.is_empty() {
+        return f64::NAN;
+    }
+    let q = q.clamp(0.0, 1.0);
+    let rank = ((q * data.len() as f64).ceil() as usize).clamp(1, data.len());
+    data[rank - 1]
 }
 
-pub fn f64_from_stat(map: &HashMap<String, serde_json::Value>, key: &str) -> Option<f64> {
-    map.get(key).and_then(|v| v.as_f64())
+/// Like [`statistics_from_sorted`] but every percentile field is computed by
+/// the nearest-rank method ([`nearest_rank_of_sorted`]) instead of linear
+/// interpolation. `avg`/`sum`/`stddev`/`variance`/`min`/`max`/`cnt` are
+/// computed identically to `statistics_from_sorted` — only the percentile
+/// fields differ. On discretized data the two methods can disagree by a
+/// whole bucket: `statistics_from_sorted(&[1,2,3,4,5]).p50 == 3.0` (an exact
+/// sample either way, since `n` is odd here), but e.g. `p90` over
+/// `[1,2,3,4,5]` is `4.6` interpolated versus `5.0` nearest-rank, because
+/// `ceil(0.9 * 5) = 5` lands nearest-rank on the last sample while linear
+/// interpolation still has `0.4` of a bucket left to blend in. `data` must
+/// already be sorted in ascending order, as with the rest of the
+/// `_from_sorted` family.
+pub fn statistics_nearest_rank(data: &[f64]) -> Statistics {
+    if data.is_empty() {
+        return statistics_from_sorted(&[]);
+    }
+
+    let cnt = data.len();
+    let sum: f64 = neumaier_sum(data);
+    let mean = sum / (cnt as f64);
+    let avg = (mean * 100.0).round() / 100.0;
+    let variance = if cnt == 1 {
+        0.0
+    } else {
+        data.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (cnt as f64)
+    };
+    let stddev = (variance.sqrt() * 100.0).round() / 100.0;
+    let pick = |q: f64| nearest_rank_of_sorted(data, q);
+
+    Statistics {
+        avg,
+        sum,
+        p1: pick(0.01),
+        p5: pick(0.05),
+        p10: pick(0.1),
+        p25: pick(0.25),
+        p30: pick(0.3),
+        p50: pick(0.5),
+        p75: pick(0.75),
+        p80: pick(0.8),
+        p90: pick(0.9),
+        p95: pick(0.95),
+        p99: pick(0.99),
+        p999: pick(0.999),
+        p9999: pick(0.9999),
+        min: data[0],
+        max: *data.last().unwrap(),
+        stddev,
+        variance,
+        cnt,
+    }
+}
+
+/// Rounding policy for [`statistics_from_sorted_cfg`]. `None` leaves every
+/// field at full `f64` precision; `Some(n)` rounds every field to `n`
+/// decimal places, `min`/`max`/`cnt` included.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StatConfig {
+    pub round_decimals: Option<u32>,
+}
+
+/// Like [`statistics_from_sorted`] but with a configurable rounding policy
+/// applied uniformly across every field. `statistics_from_sorted` itself
+/// rounds only `avg`/`stddev` to 2 decimals and leaves the rest at full
+/// precision — an inconsistency kept for backward compatibility rather than
+/// changed out from under existing callers. Reach for this function instead
+/// when a caller wants every field rounded the same way, or not rounded at
+/// all.
+pub fn statistics_from_sorted_cfg(data: &[f64], cfg: &StatConfig) -> Statistics {
+    let round = |x: f64| match cfg.round_decimals {
+        Some(decimals) => {
+            let factor = 10f64.powi(decimals as i32);
+            (x * factor).round() / factor
+        }
+        None => x,
+    };
+
+    if data.is_empty() {
+        return Statistics {
+            avg: f64::NAN,
+            sum: f64::NAN,
+            p1: f64::NAN,
+            p5: f64::NAN,
+            p10: f64::NAN,
+            p25: f64::NAN,
+            p30: f64::NAN,
+            p50: f64::NAN,
+            p75: f64::NAN,
+            p80: f64::NAN,
+            p90: f64::NAN,
+            p95: f64::NAN,
+            p99: f64::NAN,
+            p999: f64::NAN,
+            p9999: f64::NAN,
+            min: f64::NAN,
+            max: f64::NAN,
+            stddev: f64::NAN,
+            variance: f64::NAN,
+            cnt: 0,
+        };
+    }
+
+    let cnt = data.len();
+    let sum: f64 = neumaier_sum(data);
+    let mean = sum / (cnt as f64);
+    let variance = if cnt == 1 {
+        0.0
+    } else {
+        data.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (cnt as f64)
+    };
+    let pick = |q: f64| round(quantile_of_sorted(data, q, QuantileInterpolation::Linear));
+
+    Statistics {
+        avg: round(mean),
+        sum: round(sum),
+        p1: pick(0.01),
+        p5: pick(0.05),
+        p10: pick(0.1),
+        p25: pick(0.25),
+        p30: pick(0.3),
+        p50: pick(0.5),
+        p75: pick(0.75),
+        p80: pick(0.8),
+        p90: pick(0.9),
+        p95: pick(0.95),
+        p99: pick(0.99),
+        p999: pick(0.999),
+        p9999: pick(0.9999),
+        min: round(data[0]),
+        max: round(*data.last().unwrap()),
+        stddev: round(variance.sqrt()),
+        variance: round(variance),
+        cnt,
+    }
+}
+
+/// Like [`quantile_of_sorted`] but reading from an `f32`-sorted buffer,
+/// upcasting only the two bracketing samples (and the interpolation weight)
+/// to `f64`. Kept alongside `quantile_of_sorted` rather than making either
+/// generic — on-device agents storing `f32` samples is the one caller that
+/// needs this path, and it must never allocate an `f64` copy of the data.
+fn quantile_of_sorted_f32(data: &[f32], q: f64, interp: QuantileInterpolation) -> f64 {
+    if data.is_empty() {
+        return f64::NAN;
+    }
+    if data.len() == 1 {
+        return data[0] as f64;
+    }
+    let q = q.clamp(0.0, 1.0);
+    let h = (data.len() - 1) as f64 * q;
+    let lo = h.floor() as usize;
+    let hi = h.ceil() as usize;
+    if lo == hi {
+        return data[lo] as f64;
+    }
+    let w = h - (lo as f64);
+    interp.apply(data[lo] as f64, data[hi] as f64, w)
+}
+
+/// Like [`statistics_from_sorted`] but over `f32` samples, for callers (e.g.
+/// memory-constrained on-device agents) that store latencies as `f32` and
+/// would otherwise have to upcast into an intermediate `Vec<f64>` before
+/// calling this crate, doubling memory at the worst moment. `data` must
+/// already be sorted; every arithmetic step still happens in `f64` so the
+/// returned [`Statistics`] carries no more precision loss than the `f64`
+/// path.
+pub fn statistics_from_sorted_f32(data: &[f32]) -> Statistics {
+    if data.is_empty() {
+        return statistics_from_sorted(&[]);
+    }
+
+    let cnt = data.len();
+    let sum: f64 = neumaier_sum(&data.iter().map(|&x| x as f64).collect::<Vec<f64>>());
+    let mean = sum / (cnt as f64);
+    let avg = (mean * 100.0).round() / 100.0;
+    let variance = if cnt == 1 {
+        0.0
+    } else {
+        data.iter().map(|&x| (x as f64 - mean).powi(2)).sum::<f64>() / (cnt as f64)
+    };
+    let stddev = (variance.sqrt() * 100.0).round() / 100.0;
+    let pick = |q: f64| quantile_of_sorted_f32(data, q, QuantileInterpolation::Linear);
+
+    Statistics {
+        avg,
+        sum,
+        p1: pick(0.01),
+        p5: pick(0.05),
+        p10: pick(0.1),
+        p25: pick(0.25),
+        p30: pick(0.3),
+        p50: pick(0.5),
+        p75: pick(0.75),
+        p80: pick(0.8),
+        p90: pick(0.9),
+        p95: pick(0.95),
+        p99: pick(0.99),
+        p999: pick(0.999),
+        p9999: pick(0.9999),
+        min: data[0] as f64,
+        max: *data.last().unwrap() as f64,
+        stddev,
+        variance,
+        cnt,
+    }
+}
+
+/// Like [`statistics_from_vec`] but taking ownership of an `f32` buffer,
+/// sorting it in place (half the memory a `Vec<f64>` sort would need) before
+/// delegating to [`statistics_from_sorted_f32`].
+pub fn statistics_from_vec_f32(mut data: Vec<f32>) -> Statistics {
+    data.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    statistics_from_sorted_f32(&data)
+}
+
+pub fn statistics_from_vec(data: Vec<f64>) -> Statistics {
+    statistics_from_vec_with_interp(data, QuantileInterpolation::Linear)
+}
+
+/// A NaN sample is tolerated, not rejected: `data` is sorted with NaN pushed
+/// deterministically to the end (see [`cmp_nan_last`]), so any percentile
+/// that falls past it reads back as NaN rather than the whole call failing.
+/// Use [`statistics_from_vec_filtered`] to drop NaNs first, or
+/// [`try_statistics_from_vec_strict`] to reject them outright.
+pub fn statistics_from_vec_with_interp(
+    mut data: Vec<f64>,
+    interp: QuantileInterpolation,
+) -> Statistics {
+    data.sort_by(cmp_nan_last);
+    statistics_from_sorted_with_interp(&data, interp)
+}
+
+/// Total order over `f64` that places NaN after every other value (including
+/// `+inf`), used in place of the crate's usual `partial_cmp(...).unwrap_or(Ordering::Equal)`
+/// sort wherever NaN's position needs to be deterministic rather than
+/// arbitrary — `unwrap_or(Ordering::Equal)` treats a NaN as tied with
+/// whatever it's compared against, which scatters NaNs throughout the sorted
+/// output depending on the sort algorithm's comparison order, silently
+/// shifting percentiles that land near one.
+fn cmp_nan_last(a: &f64, b: &f64) -> Ordering {
+    match a.partial_cmp(b) {
+        Some(ord) => ord,
+        None => match (a.is_nan(), b.is_nan()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) => unreachable!("partial_cmp only fails on NaN"),
+        },
+    }
+}
+
+/// Like [`statistics_from_vec`] but rejecting `data` outright if it contains
+/// any `NaN` sample, for callers who would rather fail loudly at the
+/// boundary than have a `NaN` silently degrade percentiles downstream.
+pub fn try_statistics_from_vec_strict(data: Vec<f64>) -> Result<Statistics, StatError> {
+    if data.iter().any(|x| x.is_nan()) {
+        return Err(StatError::ContainsNaN);
+    }
+    Ok(statistics_from_vec(data))
+}
+
+/// [`Statistics`] over a pre-bucketed distribution given as `(value, weight)`
+/// pairs, e.g. a node-reported histogram summary where expanding every
+/// bucket back into raw samples would waste memory. `weight` is a sample
+/// count and need not be an integer (a weighted average of several nodes'
+/// histograms can land on a fraction). Percentiles walk cumulative weight
+/// share the same way [`WeightedBruteQuantileState::quantile`](crate::quantile_brute::WeightedBruteQuantileState::quantile)
+/// does, generalized to pick every stored percentile in one pass over the
+/// sorted pairs rather than one pass per call.
+///
+/// Returns the empty (`NaN`, `cnt: 0`) `Statistics` if the total weight is
+/// zero — an empty `pairs`, or one where every weight is zero, carries no
+/// distribution to summarize. Returns [`StatError::NegativeWeight`] if any
+/// pair has a negative weight, which has no meaning as a cumulative share.
+pub fn try_weighted_statistics(pairs: &[(f64, f64)]) -> Result<Statistics, StatError> {
+    if pairs.iter().any(|&(_, weight)| weight < 0.0) {
+        return Err(StatError::NegativeWeight);
+    }
+
+    let mut sorted: Vec<(f64, f64)> = pairs.iter().copied().filter(|&(_, weight)| weight > 0.0).collect();
+    let total_weight: f64 = sorted.iter().map(|&(_, weight)| weight).sum();
+    if total_weight <= 0.0 {
+        return Ok(statistics_from_sorted(&[]));
+    }
+    sorted.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+
+    let weighted_sum: f64 = sorted.iter().map(|&(value, weight)| value * weight).sum();
+    let mean = weighted_sum / total_weight;
+    let avg = (mean * 100.0).round() / 100.0;
+    let variance =
+        sorted.iter().map(|&(value, weight)| weight * (value - mean).powi(2)).sum::<f64>() / total_weight;
+    let stddev = (variance.sqrt() * 100.0).round() / 100.0;
+
+    let pick = |q: f64| {
+        let target = q * total_weight;
+        let mut cumulative = 0.0;
+        for &(value, weight) in &sorted {
+            cumulative += weight;
+            if cumulative >= target {
+                return value;
+            }
+        }
+        sorted.last().unwrap().0
+    };
+
+    Ok(Statistics {
+        avg,
+        sum: weighted_sum,
+        p1: pick(0.01),
+        p5: pick(0.05),
+        p10: pick(0.1),
+        p25: pick(0.25),
+        p30: pick(0.3),
+        p50: pick(0.5),
+        p75: pick(0.75),
+        p80: pick(0.8),
+        p90: pick(0.9),
+        p95: pick(0.95),
+        p99: pick(0.99),
+        p999: pick(0.999),
+        p9999: pick(0.9999),
+        min: sorted.first().unwrap().0,
+        max: sorted.last().unwrap().0,
+        stddev,
+        variance,
+        cnt: total_weight.round() as usize,
+    })
+}
+
+/// Like [`statistics_from_vec`] but parallelizing the dominant `O(n log n)`
+/// sort with rayon, for the tens-of-millions-of-sample aggregate runs where
+/// that sort is the bottleneck. The subsequent sum/variance passes and
+/// percentile picking stay sequential — `O(n)` and cheap by comparison — and
+/// produce output identical to the sequential path.
+#[cfg(feature = "rayon")]
+pub fn statistics_from_vec_parallel(mut data: Vec<f64>) -> Statistics {
+    use rayon::prelude::*;
+
+    data.par_sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    statistics_from_sorted_with_interp(&data, QuantileInterpolation::Linear)
+}
+
+/// Collect `iter` and compute exact [`Statistics`] over it, saving the caller
+/// an intermediate `Vec` allocation of their own. This is still `O(n log n)`
+/// for the sort inside [`statistics_from_vec`] — it only removes the extra
+/// collect the caller would otherwise have to do first.
+pub fn statistics_from_iter(iter: impl IntoIterator<Item = f64>) -> Statistics {
+    statistics_from_vec(iter.into_iter().collect())
+}
+
+/// The unit a [`std::time::Duration`] is scaled to before it becomes an `f64`
+/// sample, so every caller of [`statistics_from_durations`] (or
+/// [`QuantileEstimator::insert_duration`](crate::estimator::QuantileEstimator::insert_duration))
+/// converts durations the same way instead of some using millis and others
+/// micros.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeUnit {
+    Seconds,
+    Millis,
+    Micros,
+    Nanos,
+}
+
+impl TimeUnit {
+    /// Scale `d` to a float in this unit. `Nanos` goes through `as_nanos`
+    /// rather than `as_secs_f64() * 1e9` to avoid losing precision on
+    /// durations that don't fit exactly in an `f64` number of seconds.
+    pub fn scale(self, d: std::time::Duration) -> f64 {
+        match self {
+            TimeUnit::Seconds => d.as_secs_f64(),
+            TimeUnit::Millis => d.as_secs_f64() * 1_000.0,
+            TimeUnit::Micros => d.as_secs_f64() * 1_000_000.0,
+            TimeUnit::Nanos => d.as_nanos() as f64,
+        }
+    }
+}
+
+/// Like [`statistics_from_vec`] but taking [`std::time::Duration`] samples
+/// directly, scaled to `unit`. Centralizes the millis/micros/nanos
+/// conversion so it isn't hand-rolled (and occasionally off by a factor of
+/// 1000) at every call site.
+pub fn statistics_from_durations(data: &[std::time::Duration], unit: TimeUnit) -> Statistics {
+    statistics_from_vec(data.iter().map(|&d| unit.scale(d)).collect())
+}
+
+/// Drop NaN/+-inf samples (e.g. a failed-sample sentinel or a timeout
+/// placeholder from the collector) before computing `Statistics`, returning
+/// the dropped count alongside so the caller can log it.
+pub fn statistics_from_vec_filtered(data: Vec<f64>) -> (Statistics, usize) {
+    let original = data.len();
+    let finite: Vec<f64> = data.into_iter().filter(|x| x.is_finite()).collect();
+    let dropped = original - finite.len();
+    (statistics_from_vec(finite), dropped)
+}
+
+/// Approximate `Statistics` over a stream too large to sort, by feeding every
+/// sample into a [`TDigestQuantileState`](crate::quantile_tdigest::TDigestQuantileState)
+/// instead of materializing and sorting a `Vec`.
+pub fn statistics_approx_from_iter(iter: impl IntoIterator<Item = f64>) -> Statistics {
+    let mut digest = crate::quantile_tdigest::TDigestQuantileState::new(0);
+    for x in iter {
+        digest.insert(x);
+    }
+    digest.merge();
+    crate::estimator::statistics_from_estimator(&digest)
+}
+
+pub fn f64_from_stat(map: &HashMap<String, serde_json::Value>, key: &str) -> Option<f64> {
+    map.get(key).and_then(json_value_to_f64)
+}
+
+/// Pull `key` out of every element of `maps` via [`f64_from_stat`] and roll
+/// the results up into one [`Statistics`] via [`statistics_from_vec`] — the
+/// "aggregate all nodes" step an analyzer over per-node stat dumps otherwise
+/// repeats at every call site. Alongside the aggregate, returns how many
+/// maps were missing `key` (or had a non-numeric value for it) so the caller
+/// can detect partial data instead of silently rolling up a subset.
+pub fn aggregate_from_stat_maps(
+    maps: &[HashMap<String, serde_json::Value>],
+    key: &str,
+) -> (Statistics, usize) {
+    let mut values = Vec::with_capacity(maps.len());
+    let mut missing = 0;
+    for map in maps {
+        match f64_from_stat(map, key) {
+            Some(v) => values.push(v),
+            None => missing += 1,
+        }
+    }
+    (statistics_from_vec(values), missing)
+}
+
+/// A JSON number, or a string-encoded one (some ingested payloads carry
+/// `"123.4"` instead of `123.4`). `None` for any other JSON type, so callers
+/// never panic on an unexpected shape.
+fn json_value_to_f64(value: &serde_json::Value) -> Option<f64> {
+    match value {
+        serde_json::Value::Number(n) => n.as_f64(),
+        serde_json::Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Like [`f64_from_stat`] but `path` is a dot-separated route into nested
+/// objects (e.g. `"latency.p99"`), for payloads that group related fields
+/// under a section instead of keeping everything at the top level. Returns
+/// `None` rather than panicking if any segment is missing or isn't an object,
+/// or if the leaf isn't a parseable number.
+pub fn f64_from_stat_path(map: &HashMap<String, serde_json::Value>, path: &str) -> Option<f64> {
+    let mut segments = path.split('.');
+    let mut current = map.get(segments.next()?)?;
+    for segment in segments {
+        current = current.get(segment)?;
+    }
+    json_value_to_f64(current)
+}
+
+/// The inverse of [`f64_from_stat`]: flatten `stat` into the same
+/// `HashMap<String, Value>` shape, keyed by field name, so a caller that
+/// reads one node's stats via `f64_from_stat` can re-emit an aggregated one
+/// in a shape other tools already consume. NaN fields serialize as JSON
+/// `null`, matching `Statistics`'s own [`Serialize`] impl.
+pub fn statistics_to_map(stat: &Statistics) -> HashMap<String, serde_json::Value> {
+    let num = |x: f64| if x.is_nan() { serde_json::Value::Null } else { serde_json::json!(x) };
+    HashMap::from([
+        ("avg".to_string(), num(stat.avg)),
+        ("p1".to_string(), num(stat.p1)),
+        ("p5".to_string(), num(stat.p5)),
+        ("p10".to_string(), num(stat.p10)),
+        ("p25".to_string(), num(stat.p25)),
+        ("p30".to_string(), num(stat.p30)),
+        ("p50".to_string(), num(stat.p50)),
+        ("p75".to_string(), num(stat.p75)),
+        ("p80".to_string(), num(stat.p80)),
+        ("p90".to_string(), num(stat.p90)),
+        ("p95".to_string(), num(stat.p95)),
+        ("p99".to_string(), num(stat.p99)),
+        ("p999".to_string(), num(stat.p999)),
+        ("p9999".to_string(), num(stat.p9999)),
+        ("min".to_string(), num(stat.min)),
+        ("max".to_string(), num(stat.max)),
+        ("stddev".to_string(), num(stat.stddev)),
+        ("variance".to_string(), num(stat.variance)),
+        ("cnt".to_string(), serde_json::json!(stat.cnt)),
+    ])
+}
+
+/// The median absolute deviation: the median of `|x - median(data)|`, a
+/// spread measure even more outlier-resistant than the IQR since both the
+/// center and the deviations are medians rather than means. `NaN` on empty
+/// input.
+pub fn median_absolute_deviation(data: &[f64]) -> f64 {
+    if data.is_empty() {
+        return f64::NAN;
+    }
+    let mut sorted = data.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    let median = quantile_of_sorted(&sorted, 0.5, QuantileInterpolation::Linear);
+    let mut deviations: Vec<f64> = data.iter().map(|x| (x - median).abs()).collect();
+    deviations.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    quantile_of_sorted(&deviations, 0.5, QuantileInterpolation::Linear)
+}
+
+/// [`median_absolute_deviation`] scaled by the constant `1.4826` that makes
+/// it a consistent estimator of the standard deviation under a normal
+/// distribution, so it can be compared directly against `stddev`.
+pub fn mad_normal(data: &[f64]) -> f64 {
+    median_absolute_deviation(data) * 1.4826
+}
+
+/// Fisher-Pearson adjusted sample skewness: positive for a right-skewed
+/// (long upper tail) distribution, which is the common shape for latency
+/// data. `NaN` on fewer than 3 samples or zero variance.
+pub fn skewness(data: &[f64]) -> f64 {
+    let n = data.len();
+    if n < 3 {
+        return f64::NAN;
+    }
+    let mean = data.iter().sum::<f64>() / (n as f64);
+    let m2 = data.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n as f64);
+    if m2 == 0.0 {
+        return f64::NAN;
+    }
+    let m3 = data.iter().map(|x| (x - mean).powi(3)).sum::<f64>() / (n as f64);
+    let g1 = m3 / m2.powf(1.5);
+    let n = n as f64;
+    (n * n / ((n - 1.0) * (n - 2.0))) * g1
+}
+
+/// Excess kurtosis (the normal distribution's kurtosis of `3.0` subtracted
+/// off, so `0.0` means normal-like tails): positive means heavier-than-normal
+/// tails, the common case for latency data with occasional severe outliers.
+/// `NaN` on fewer than 4 samples or zero variance.
+pub fn kurtosis(data: &[f64]) -> f64 {
+    let n = data.len();
+    if n < 4 {
+        return f64::NAN;
+    }
+    let mean = data.iter().sum::<f64>() / (n as f64);
+    let m2 = data.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n as f64);
+    if m2 == 0.0 {
+        return f64::NAN;
+    }
+    let m4 = data.iter().map(|x| (x - mean).powi(4)).sum::<f64>() / (n as f64);
+    m4 / (m2 * m2) - 3.0
+}
+
+/// Sarle's bimodality coefficient: `(skewness^2 + 1) / (kurtosis + correction)`,
+/// using [`kurtosis`]'s excess form directly and the usual finite-sample
+/// adjustment `correction = 3*(n-1)^2 / ((n-2)*(n-3))`. The uniform
+/// distribution's population value works out to `5/9 ≈ 0.555`, the
+/// threshold [`is_likely_bimodal`] uses; higher values point towards a
+/// bimodal or multimodal sample, lower towards unimodal. `NaN` under the
+/// same conditions [`skewness`]/[`kurtosis`] are, i.e. fewer than 4 samples
+/// or zero variance.
+pub fn bimodality_coefficient(data: &[f64]) -> f64 {
+    let n = data.len();
+    if n < 4 {
+        return f64::NAN;
+    }
+    let skew = skewness(data);
+    let excess_kurtosis = kurtosis(data);
+    if skew.is_nan() || excess_kurtosis.is_nan() {
+        return f64::NAN;
+    }
+    let n = n as f64;
+    let correction = 3.0 * (n - 1.0).powi(2) / ((n - 2.0) * (n - 3.0));
+    (skew.powi(2) + 1.0) / (excess_kurtosis + correction)
+}
+
+/// Threshold on [`bimodality_coefficient`] above which the sample is
+/// "probably bimodal (or multimodal)" by Sarle & Peck's rule of thumb: the
+/// coefficient for the uniform distribution, `5/9`. A single set of
+/// percentiles can hide a fast-path/slow-path split in latency data; a
+/// `true` here is a prompt to break the analysis down further (e.g. by
+/// operation), rather than a hard proof of two distinct modes.
+pub fn is_likely_bimodal(data: &[f64]) -> bool {
+    bimodality_coefficient(data) > 5.0 / 9.0
+}
+
+/// The geometric mean of strictly-positive `data`: `NaN` on empty input or if
+/// any sample is `<= 0`, since the geometric mean of a non-positive value is
+/// undefined over the reals.
+pub fn geometric_mean(data: &[f64]) -> f64 {
+    if data.is_empty() || data.iter().any(|&x| x <= 0.0) {
+        return f64::NAN;
+    }
+    let sum_ln: f64 = data.iter().map(|x| x.ln()).sum();
+    (sum_ln / (data.len() as f64)).exp()
+}
+
+/// The harmonic mean of strictly-positive `data`, e.g. averaging throughputs
+/// (requests/sec) where the arithmetic mean would over-weight the fast runs.
+/// `NaN` on empty input or any non-positive sample.
+pub fn harmonic_mean(data: &[f64]) -> f64 {
+    if data.is_empty() || data.iter().any(|&x| x <= 0.0) {
+        return f64::NAN;
+    }
+    let sum_recip: f64 = data.iter().map(|x| 1.0 / x).sum();
+    (data.len() as f64) / sum_recip
+}
+
+/// The interquartile range (`p75 - p25`) of `data`, a spread measure
+/// unaffected by tail outliers the way `max - min` is. `NaN` on empty input.
+pub fn iqr(data: &[f64]) -> f64 {
+    if data.is_empty() {
+        return f64::NAN;
+    }
+    let mut sorted = data.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    let p25 = quantile_of_sorted(&sorted, 0.25, QuantileInterpolation::Linear);
+    let p75 = quantile_of_sorted(&sorted, 0.75, QuantileInterpolation::Linear);
+    p75 - p25
+}
+
+/// Samples more than `k` IQRs below `p25` (`.0`) or above `p75` (`.1`)
+/// (Tukey's fences; `k = 1.5` is the conventional "mild outlier" threshold),
+/// each preserving its original order. Both empty on fewer than 2 samples,
+/// since the IQR is undefined.
+pub fn outliers(data: &[f64], k: f64) -> (Vec<f64>, Vec<f64>) {
+    if data.len() < 2 {
+        return (Vec::new(), Vec::new());
+    }
+    let mut sorted = data.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    let p25 = quantile_of_sorted(&sorted, 0.25, QuantileInterpolation::Linear);
+    let p75 = quantile_of_sorted(&sorted, 0.75, QuantileInterpolation::Linear);
+    let span = k * (p75 - p25);
+    let (lo, hi) = (p25 - span, p75 + span);
+    let low = data.iter().copied().filter(|&x| x < lo).collect();
+    let high = data.iter().copied().filter(|&x| x > hi).collect();
+    (low, high)
+}
+
+/// Every distinct value in `data` with how many times it occurs, sorted
+/// ascending by value. Grouping is by exact bit-for-bit equality (`==`), not
+/// a tolerance window: latencies from a discretized source (timer
+/// granularity, quantized buckets) repeat exactly, so exact equality is the
+/// right grouping and avoids picking an arbitrary tolerance. `NaN` samples
+/// are excluded, since `NaN != NaN` makes "how many times does NaN occur"
+/// ill-defined under this scheme.
+pub fn value_counts(data: &[f64]) -> Vec<(f64, usize)> {
+    let mut sorted: Vec<f64> = data.iter().copied().filter(|x| !x.is_nan()).collect();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    let mut counts: Vec<(f64, usize)> = Vec::new();
+    for x in sorted {
+        match counts.last_mut() {
+            Some((value, count)) if *value == x => *count += 1,
+            _ => counts.push((x, 1)),
+        }
+    }
+    counts
+}
+
+/// The most frequently occurring value in `data` and how many times it
+/// occurs, ties broken by the smallest value. `None` on empty input (or
+/// input consisting entirely of `NaN`, per [`value_counts`]'s exclusion of
+/// it).
+pub fn mode(data: &[f64]) -> Option<(f64, usize)> {
+    value_counts(data)
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then(b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal)))
+}
+
+/// The mean after dropping the lowest and highest `trim_fraction` of sorted
+/// samples from each tail, e.g. `trim_fraction = 0.1` drops the bottom and
+/// top 10%. Robust to the occasional wildly-off outlier a plain average would
+/// be skewed by. `trim_fraction` is clamped to `[0.0, 0.49]`; `NaN` on empty
+/// input.
+pub fn trimmed_mean(data: &[f64], trim_fraction: f64) -> f64 {
+    if data.is_empty() {
+        return f64::NAN;
+    }
+    let trim_fraction = trim_fraction.clamp(0.0, 0.49);
+    let mut sorted = data.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    let cut = ((sorted.len() as f64) * trim_fraction).floor() as usize;
+    let kept = &sorted[cut..sorted.len() - cut];
+    if kept.is_empty() {
+        return f64::NAN;
+    }
+    kept.iter().sum::<f64>() / (kept.len() as f64)
+}
+
+/// Like [`trimmed_mean`] but instead of dropping the tails, clamps them to
+/// the boundary value so every sample still contributes — "winsorizing"
+/// rather than discarding the extremes.
+pub fn winsorized_mean(data: &[f64], trim_fraction: f64) -> f64 {
+    if data.is_empty() {
+        return f64::NAN;
+    }
+    let trim_fraction = trim_fraction.clamp(0.0, 0.49);
+    let mut sorted = data.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    let cut = ((sorted.len() as f64) * trim_fraction).floor() as usize;
+    if cut == 0 {
+        return sorted.iter().sum::<f64>() / (sorted.len() as f64);
+    }
+    let lo = sorted[cut];
+    let hi = sorted[sorted.len() - 1 - cut];
+    let sum: f64 = sorted
+        .iter()
+        .map(|&x| if x < lo { lo } else if x > hi { hi } else { x })
+        .sum();
+    sum / (sorted.len() as f64)
+}
+
+/// Two-sample Kolmogorov-Smirnov test: is `a` and `b` drawn from the same
+/// distribution? Returns `(D, p_value)` where `D` is the maximum gap between
+/// the two samples' empirical CDFs (0.0 for identical distributions) and
+/// `p_value` is the asymptotic Kolmogorov approximation, small when the two
+/// distributions differ significantly. `(NaN, NaN)` if either input is empty.
+pub fn ks_two_sample(a: &[f64], b: &[f64]) -> (f64, f64) {
+    if a.is_empty() || b.is_empty() {
+        return (f64::NAN, f64::NAN);
+    }
+    let mut sa = a.to_vec();
+    sa.sort_by(|x, y| x.partial_cmp(y).unwrap_or(Ordering::Equal));
+    let mut sb = b.to_vec();
+    sb.sort_by(|x, y| x.partial_cmp(y).unwrap_or(Ordering::Equal));
+    let (na, nb) = (sa.len() as f64, sb.len() as f64);
+
+    // Walk both sorted samples in merged order, tracking each side's
+    // empirical CDF at the current value; the maximum gap between them is D.
+    // A tied value must advance *both* pointers past every occurrence of it
+    // before the gap is recorded — recording after only one side has stepped
+    // would compare CDFs at two different values instead of the same one,
+    // manufacturing a spurious gap between identical distributions.
+    let (mut i, mut j) = (0usize, 0usize);
+    let mut d_max = 0.0f64;
+    while i < sa.len() && j < sb.len() {
+        if sa[i] < sb[j] {
+            i += 1;
+        } else if sa[i] > sb[j] {
+            j += 1;
+        } else {
+            let v = sa[i];
+            while i < sa.len() && sa[i] == v {
+                i += 1;
+            }
+            while j < sb.len() && sb[j] == v {
+                j += 1;
+            }
+        }
+        let gap = (i as f64 / na - j as f64 / nb).abs();
+        if gap > d_max {
+            d_max = gap;
+        }
+    }
+
+    let en = ((na * nb) / (na + nb)).sqrt();
+    let p_value = kolmogorov_p_value((en + 0.12 + 0.11 / en) * d_max);
+    (d_max, p_value)
+}
+
+/// The asymptotic Kolmogorov distribution's survival function `Q_KS(t) = 2 *
+/// sum_{k=1..inf} (-1)^(k-1) * exp(-2 k^2 t^2)`, truncated once terms become
+/// negligible.
+fn kolmogorov_p_value(t: f64) -> f64 {
+    let mut sum = 0.0;
+    for k in 1..=100 {
+        let sign = if k % 2 == 1 { 1.0 } else { -1.0 };
+        let term = sign * (-2.0 * (k as f64).powi(2) * t * t).exp();
+        sum += term;
+        if term.abs() < 1e-10 {
+            break;
+        }
+    }
+    (2.0 * sum).clamp(0.0, 1.0)
+}
+
+/// The standard normal CDF, `P(Z <= x)` for `Z ~ N(0, 1)`, via the
+/// Abramowitz-Stegun approximation to the error function (accurate to about
+/// `1.5e-7`). The building block for [`mann_whitney_u`]'s normal-approximation
+/// p-value.
+fn normal_cdf(x: f64) -> f64 {
+    0.5 * (1.0 + erf(x / std::f64::consts::SQRT_2))
+}
+
+/// Abramowitz & Stegun formula 7.1.26.
+fn erf(x: f64) -> f64 {
+    let sign = if x < 0.0 { -1.0 } else { 1.0 };
+    let x = x.abs();
+    let a1 = 0.254829592;
+    let a2 = -0.284496736;
+    let a3 = 1.421413741;
+    let a4 = -1.453152027;
+    let a5 = 1.061405429;
+    let p = 0.3275911;
+    let t = 1.0 / (1.0 + p * x);
+    let y = 1.0 - (((((a5 * t + a4) * t) + a3) * t + a2) * t + a1) * t * (-x * x).exp();
+    sign * y
+}
+
+/// Assign 1-based ranks to `values`, averaging ranks within a tied group (the
+/// standard tie-handling for rank-based tests). Returns the ranks alongside
+/// the tie-correction term `sum(t_i^3 - t_i)` over every tied group of size
+/// `t_i`, needed by [`mann_whitney_u`]'s variance.
+fn ranks_with_ties(values: &[f64]) -> (Vec<f64>, f64) {
+    let n = values.len();
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|&i, &j| values[i].partial_cmp(&values[j]).unwrap_or(Ordering::Equal));
+
+    let mut ranks = vec![0.0; n];
+    let mut tie_term = 0.0;
+    let mut i = 0;
+    while i < n {
+        let mut j = i;
+        while j + 1 < n && values[order[j + 1]] == values[order[i]] {
+            j += 1;
+        }
+        let avg_rank = ((i + 1) + (j + 1)) as f64 / 2.0;
+        for &idx in &order[i..=j] {
+            ranks[idx] = avg_rank;
+        }
+        let tie_size = (j - i + 1) as f64;
+        tie_term += tie_size.powi(3) - tie_size;
+        i = j + 1;
+    }
+    (ranks, tie_term)
+}
+
+/// Mann-Whitney U test: a non-parametric alternative to a t-test for whether
+/// `a` and `b` are drawn from the same distribution, without assuming either
+/// is normal — the right tool for latency data. Returns `(U, p_value)` where
+/// `U` is the U statistic for `a` and `p_value` is a two-tailed
+/// normal-approximation p-value with tie correction. `(NaN, NaN)` if either
+/// input is empty; `p_value` is `1.0` in the degenerate case where every
+/// value ties (zero variance).
+pub fn mann_whitney_u(a: &[f64], b: &[f64]) -> (f64, f64) {
+    if a.is_empty() || b.is_empty() {
+        return (f64::NAN, f64::NAN);
+    }
+    let (na, nb) = (a.len() as f64, b.len() as f64);
+    let n = na + nb;
+
+    let mut combined = Vec::with_capacity(a.len() + b.len());
+    combined.extend_from_slice(a);
+    combined.extend_from_slice(b);
+    let (ranks, tie_term) = ranks_with_ties(&combined);
+
+    let rank_sum_a: f64 = ranks[..a.len()].iter().sum();
+    let u1 = rank_sum_a - na * (na + 1.0) / 2.0;
+
+    let mu_u = na * nb / 2.0;
+    let sigma_u2 = na * nb / 12.0 * ((n + 1.0) - tie_term / (n * (n - 1.0)));
+    if sigma_u2 <= 0.0 {
+        return (u1, 1.0);
+    }
+    let z = (u1 - mu_u) / sigma_u2.sqrt();
+    let p_value = (2.0 * (1.0 - normal_cdf(z.abs()))).clamp(0.0, 1.0);
+    (u1, p_value)
+}
+
+/// A tiny xorshift64 PRNG, kept internal so [`bootstrap_ci`] carries no
+/// external RNG dependency (mirrors the one in
+/// [`quantile_reservoir`](crate::quantile_reservoir)).
+struct BootstrapRng {
+    state: u64,
+}
+
+impl BootstrapRng {
+    fn new(seed: u64) -> Self {
+        Self {
+            state: if seed == 0 { 0x9E37_79B9_7F4A_7C15 } else { seed },
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+        x
+    }
+
+    /// A uniform index in `0..n`.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+/// Bootstrap confidence interval for the `q` quantile of `data`: resample
+/// `data` with replacement `resamples` times, take the `q` quantile of each
+/// resample, and return the `[alpha/2, 1 - alpha/2]` percentile interval of
+/// those `resamples` estimates. `seed` makes the resampling (and therefore
+/// the returned interval) deterministic, e.g. `bootstrap_ci(&latencies, 0.99,
+/// 2000, 0.05, 42)` turns "p99 = 420ms" into "p99 = 420ms (95% CI
+/// 390-460)". `(NaN, NaN)` if `data` is empty or `resamples` is `0`.
+pub fn bootstrap_ci(data: &[f64], q: f64, resamples: usize, alpha: f64, seed: u64) -> (f64, f64) {
+    if data.is_empty() || resamples == 0 {
+        return (f64::NAN, f64::NAN);
+    }
+    let mut rng = BootstrapRng::new(seed);
+    let mut estimates = Vec::with_capacity(resamples);
+    let mut resample = Vec::with_capacity(data.len());
+    for _ in 0..resamples {
+        resample.clear();
+        resample.extend((0..data.len()).map(|_| data[rng.below(data.len())]));
+        estimates.push(exact_quantile(&resample, q, QuantileInterpolation::Linear));
+    }
+    let lo = exact_quantile(&estimates, alpha / 2.0, QuantileInterpolation::Linear);
+    let hi = exact_quantile(&estimates, 1.0 - alpha / 2.0, QuantileInterpolation::Linear);
+    (lo, hi)
+}
+
+/// `n` evenly spaced points `(value, cumulative_probability)` tracing the
+/// empirical CDF of `data`, `cumulative_probability` running from `0.0` to
+/// `1.0` inclusive in `n - 1` equal steps. Standardizes the "call `quantile`
+/// at 100 points to draw a CDF" pattern that otherwise gets reinvented per
+/// caller. Empty if `data` is empty or `n < 2`, since a single point can't
+/// trace a curve.
+pub fn cdf_points(data: &[f64], n: usize) -> Vec<(f64, f64)> {
+    if data.is_empty() || n < 2 {
+        return Vec::new();
+    }
+    let mut sorted = data.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    (0..n)
+        .map(|i| {
+            let q = i as f64 / (n - 1) as f64;
+            (quantile_of_sorted(&sorted, q, QuantileInterpolation::Linear), q)
+        })
+        .collect()
+}
+
+/// Logarithmically bucketed histogram of `data`, `buckets_per_decade` buckets
+/// per order of magnitude, spanning from the smallest to the largest positive
+/// sample. Returns `(bucket_low, bucket_high, count)` triples in ascending
+/// order; latency spans several orders of magnitude, so this shows the shape
+/// of the distribution far better than a linear bucketing would. Samples
+/// `<= 0.0` are counted separately in a leading `(f64::NEG_INFINITY, 0.0,
+/// count)` triple rather than dropped, since a latency of zero (or a bad
+/// sample) is still worth surfacing. Returns just that leading triple (or an
+/// empty `Vec` if there's nothing non-positive either) when `data` has no
+/// positive samples or `buckets_per_decade` is `0`.
+pub fn log_histogram(data: &[f64], buckets_per_decade: usize) -> Vec<(f64, f64, usize)> {
+    let mut out = Vec::new();
+    let positive: Vec<f64> = data.iter().copied().filter(|x| *x > 0.0).collect();
+    let non_positive = data.len() - positive.len();
+    if non_positive > 0 {
+        out.push((f64::NEG_INFINITY, 0.0, non_positive));
+    }
+    if positive.is_empty() || buckets_per_decade == 0 {
+        return out;
+    }
+
+    let log_min = positive.iter().cloned().fold(f64::INFINITY, f64::min).log10();
+    let log_max = positive
+        .iter()
+        .cloned()
+        .fold(f64::NEG_INFINITY, f64::max)
+        .log10();
+    let step = 1.0 / buckets_per_decade as f64;
+    let bucket_count = (((log_max - log_min) / step).ceil() as usize).max(1);
+
+    let mut counts = vec![0usize; bucket_count];
+    for &x in &positive {
+        let idx = (((x.log10() - log_min) / step) as usize).min(bucket_count - 1);
+        counts[idx] += 1;
+    }
+
+    out.extend(counts.into_iter().enumerate().map(|(i, count)| {
+        let low = 10f64.powf(log_min + i as f64 * step);
+        let high = 10f64.powf(log_min + (i + 1) as f64 * step);
+        (low, high, count)
+    }));
+    out
+}
+
+/// Returned by [`histogram_with_edges`] when `edges` is not strictly
+/// increasing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidEdges;
+
+impl fmt::Display for InvalidEdges {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "histogram edges must be strictly increasing")
+    }
+}
+
+impl std::error::Error for InvalidEdges {}
+
+/// Fixed, human-chosen bucket boundaries for SLA dashboards, complementing
+/// [`log_histogram`]'s automatic log-scale buckets. `edges` defines
+/// `edges.len() - 1` inclusive-low/exclusive-high buckets; the returned
+/// `Vec<usize>` has `edges.len() + 1` entries: an underflow count (values `<
+/// edges[0]`), one count per bucket, and an overflow count (values `>=
+/// edges[edges.len() - 1]`). Errors with [`InvalidEdges`] unless `edges` is
+/// strictly increasing.
+pub fn histogram_with_edges(data: &[f64], edges: &[f64]) -> Result<Vec<usize>, InvalidEdges> {
+    if !edges.windows(2).all(|w| w[0] < w[1]) {
+        return Err(InvalidEdges);
+    }
+    let mut counts = vec![0usize; edges.len() + 1];
+    for &x in data {
+        let bucket = match edges.iter().position(|&edge| x < edge) {
+            Some(0) => 0,
+            Some(i) => i,
+            None => edges.len(),
+        };
+        counts[bucket] += 1;
+    }
+    Ok(counts)
+}
+
+/// Running mean/variance/min/max over a stream of samples, without storing
+/// any of them. Pair this with a [`TDigestQuantileState`](crate::quantile_tdigest::TDigestQuantileState)
+/// for percentiles — `OnlineStats` only tracks the moments.
+#[derive(Debug, Clone)]
+pub struct OnlineStats {
+    count: usize,
+    mean: f64,
+    m2: f64,
+    min: f64,
+    max: f64,
+}
+
+impl Default for OnlineStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OnlineStats {
+    pub fn new() -> Self {
+        Self {
+            count: 0,
+            mean: 0.0,
+            m2: 0.0,
+            min: f64::NAN,
+            max: f64::NAN,
+        }
+    }
+
+    /// Welford's online algorithm: `mean` and `m2` (the running sum of squared
+    /// deviations from the mean) are updated in a single pass with no
+    /// catastrophic cancellation, even as `count` grows unbounded.
+    pub fn insert(&mut self, x: f64) {
+        self.count += 1;
+        let delta = x - self.mean;
+        self.mean += delta / (self.count as f64);
+        let delta2 = x - self.mean;
+        self.m2 += delta * delta2;
+        if self.min.is_nan() || x < self.min {
+            self.min = x;
+        }
+        if self.max.is_nan() || x > self.max {
+            self.max = x;
+        }
+    }
+
+    pub fn avg(&self) -> f64 {
+        if self.count == 0 {
+            f64::NAN
+        } else {
+            self.mean
+        }
+    }
+
+    /// Population variance; `NaN` for zero samples, `0.0` for exactly one.
+    pub fn variance(&self) -> f64 {
+        if self.count == 0 {
+            f64::NAN
+        } else {
+            self.m2 / (self.count as f64)
+        }
+    }
+
+    pub fn stddev(&self) -> f64 {
+        self.variance().sqrt()
+    }
+
+    pub fn min(&self) -> f64 {
+        self.min
+    }
+
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Snapshot the running moments into a [`Statistics`], for callers that
+    /// want the familiar struct rather than calling `avg`/`variance`/... one
+    /// at a time. Percentile fields are left `NaN`: `OnlineStats` doesn't
+    /// retain samples to compute them from, so pair this with a
+    /// [`TDigestQuantileState`](crate::quantile_tdigest::TDigestQuantileState)
+    /// fed the same stream and merge in its quantiles if percentiles are
+    /// needed, as the struct docs above already recommend.
+    pub fn to_statistics(&self) -> Statistics {
+        if self.count == 0 {
+            return statistics_from_sorted(&[]);
+        }
+        Statistics {
+            avg: (self.mean * 100.0).round() / 100.0,
+            sum: self.mean * (self.count as f64),
+            p1: f64::NAN,
+            p5: f64::NAN,
+            p10: f64::NAN,
+            p25: f64::NAN,
+            p30: f64::NAN,
+            p50: f64::NAN,
+            p75: f64::NAN,
+            p80: f64::NAN,
+            p90: f64::NAN,
+            p95: f64::NAN,
+            p99: f64::NAN,
+            p999: f64::NAN,
+            p9999: f64::NAN,
+            min: self.min,
+            max: self.max,
+            stddev: (self.stddev() * 100.0).round() / 100.0,
+            variance: self.variance(),
+            cnt: self.count,
+        }
+    }
+}
+
+/// Running min/max over a stream of samples, without storing any of them or
+/// tracking the moments [`OnlineStats`] does — for callers that only need
+/// the range and want to skip the mean/variance bookkeeping.
+#[derive(Debug, Clone)]
+pub struct MinMax {
+    min: f64,
+    max: f64,
+    count: usize,
+}
+
+impl Default for MinMax {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MinMax {
+    pub fn new() -> Self {
+        Self {
+            min: f64::NAN,
+            max: f64::NAN,
+            count: 0,
+        }
+    }
+
+    /// Update the running range with `x`. NaN is ignored rather than
+    /// poisoning `min`/`max`, matching [`OnlineStats::insert`]'s treatment of
+    /// non-finite input.
+    pub fn insert(&mut self, x: f64) {
+        if x.is_nan() {
+            return;
+        }
+        self.count += 1;
+        if self.min.is_nan() || x < self.min {
+            self.min = x;
+        }
+        if self.max.is_nan() || x > self.max {
+            self.max = x;
+        }
+    }
+
+    /// `NaN` if every inserted sample was `NaN` (or nothing was inserted).
+    pub fn min(&self) -> f64 {
+        self.min
+    }
+
+    /// `NaN` if every inserted sample was `NaN` (or nothing was inserted).
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+
+    /// `max - min`, `NaN` under the same conditions as [`min`](Self::min)/[`max`](Self::max).
+    pub fn range(&self) -> f64 {
+        self.max - self.min
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_json() {
+        let data: Vec<f64> = (1..=100).map(|i| i as f64).collect();
+        let stats = statistics_from_vec(data);
+        let json = serde_json::to_string(&stats).unwrap();
+        let back: Statistics = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.cnt, stats.cnt);
+        assert_eq!(back.p50, stats.p50);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_matches_sequential() {
+        let data: Vec<f64> = (1..=50_000).map(|i| i as f64).collect();
+        let sequential = statistics_from_vec(data.clone());
+        let parallel = statistics_from_vec_parallel(data);
+        assert_eq!(sequential.p50, parallel.p50);
+        assert_eq!(sequential.p99, parallel.p99);
+        assert_eq!(sequential.cnt, parallel.cnt);
+    }
+
+    #[test]
+    fn stddev_and_variance_match_known_sample() {
+        // 2, 4, 4, 4, 5, 5, 7, 9 has a population variance of exactly 4.0.
+        let data = vec![2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        let stats = statistics_from_vec(data);
+        assert!((stats.variance - 4.0).abs() < 1e-9, "variance was {}", stats.variance);
+        assert!((stats.stddev - 2.0).abs() < 1e-9, "stddev was {}", stats.stddev);
+    }
+
+    #[test]
+    fn single_sample_has_zero_variance() {
+        let stats = statistics_from_sorted(&[42.0]);
+        assert_eq!(stats.variance, 0.0);
+        assert_eq!(stats.stddev, 0.0);
+    }
+
+    #[test]
+    fn p25_and_p75_bracket_the_median() {
+        let data: Vec<f64> = (1..=1000).map(|i| i as f64).collect();
+        let stats = statistics_from_vec(data);
+        assert!(stats.p25 < stats.p50 && stats.p50 < stats.p75, "p25={} p50={} p75={}", stats.p25, stats.p50, stats.p75);
+        assert!(stats.p10 < stats.p25 && stats.p75 < stats.p80);
+    }
+
+    #[test]
+    fn p1_and_p5_sit_below_p10() {
+        let data: Vec<f64> = (1..=1000).map(|i| i as f64).collect();
+        let stats = statistics_from_vec(data);
+        assert!(stats.p1 < stats.p5 && stats.p5 < stats.p10, "p1={} p5={} p10={}", stats.p1, stats.p5, stats.p10);
+    }
+
+    #[test]
+    fn p9999_resolves_a_distinct_tail_point_on_large_data() {
+        let data: Vec<f64> = (1..=100_000).map(|i| i as f64).collect();
+        let stats = statistics_from_vec(data);
+        assert!(stats.p9999 > stats.p999 && stats.p9999 < stats.max, "p9999 was {}", stats.p9999);
+    }
+
+    #[test]
+    fn p9999_collapses_to_max_on_small_data() {
+        let data: Vec<f64> = (1..=100).map(|i| i as f64).collect();
+        let stats = statistics_from_vec(data);
+        // h = (n - 1) * q = 98.9901, so linear interpolation lands just
+        // short of the max rather than exactly on it.
+        assert!((stats.p9999 - stats.max).abs() < 0.01, "p9999={} max={}", stats.p9999, stats.max);
+    }
+
+    #[test]
+    fn statistics_from_vec_sorts_nan_deterministically_to_the_end() {
+        // Below the fraction of samples that are NaN, percentiles are
+        // unaffected by where the NaN ends up; min/max sit on the correct
+        // (non-NaN) end regardless.
+        let mut data: Vec<f64> = (1..=100).map(|i| i as f64).collect();
+        data.push(f64::NAN);
+        let stats = statistics_from_vec(data);
+        assert_eq!(stats.min, 1.0);
+        assert!(stats.max.is_nan(), "NaN should sort to the end and become the reported max");
+        assert!((stats.p50 - 50.5).abs() < 1e-9, "p50 was {}", stats.p50);
+    }
+
+    #[test]
+    fn try_statistics_from_vec_strict_rejects_nan() {
+        assert!(matches!(
+            try_statistics_from_vec_strict(vec![1.0, f64::NAN, 2.0]),
+            Err(StatError::ContainsNaN)
+        ));
+    }
+
+    #[test]
+    fn try_statistics_from_vec_strict_accepts_finite_data() {
+        let stats = try_statistics_from_vec_strict(vec![1.0, 2.0, 3.0]).unwrap();
+        assert_eq!(stats.cnt, 3);
+    }
+
+    #[test]
+    fn try_weighted_statistics_matches_expanded_raw_samples() {
+        let pairs = vec![(1.0, 1.0), (2.0, 2.0), (3.0, 1.0)];
+        let expanded = vec![1.0, 2.0, 2.0, 3.0];
+        let weighted = try_weighted_statistics(&pairs).unwrap();
+        let raw = statistics_from_vec(expanded);
+        assert_eq!(weighted.cnt, raw.cnt);
+        assert!((weighted.avg - raw.avg).abs() < 1e-9);
+        assert!((weighted.p50 - raw.p50).abs() < 1e-9);
+        assert_eq!(weighted.min, raw.min);
+        assert_eq!(weighted.max, raw.max);
+        assert!((weighted.sum - raw.sum).abs() < 1e-9);
+    }
+
+    #[test]
+    fn try_weighted_statistics_is_empty_on_zero_total_weight() {
+        let stats = try_weighted_statistics(&[(1.0, 0.0), (2.0, 0.0)]).unwrap();
+        assert_eq!(stats.cnt, 0);
+        assert!(stats.avg.is_nan());
+        assert!(stats.sum.is_nan());
+
+        let stats = try_weighted_statistics(&[]).unwrap();
+        assert_eq!(stats.cnt, 0);
+    }
+
+    #[test]
+    fn try_weighted_statistics_rejects_negative_weight() {
+        assert!(matches!(
+            try_weighted_statistics(&[(1.0, 1.0), (2.0, -1.0)]),
+            Err(StatError::NegativeWeight)
+        ));
+    }
+
+    #[test]
+    fn try_weighted_statistics_ignores_input_order() {
+        let ascending = try_weighted_statistics(&[(1.0, 3.0), (2.0, 1.0), (3.0, 6.0)]).unwrap();
+        let shuffled = try_weighted_statistics(&[(3.0, 6.0), (1.0, 3.0), (2.0, 1.0)]).unwrap();
+        assert_eq!(ascending.min, shuffled.min);
+        assert_eq!(ascending.max, shuffled.max);
+        assert!((ascending.avg - shuffled.avg).abs() < 1e-9);
+        assert!((ascending.p50 - shuffled.p50).abs() < 1e-9);
+    }
+
+    #[test]
+    fn neumaier_sum_stays_accurate_on_a_naive_sum_pathological_case() {
+        // The classic compensated-summation counterexample: adding 1.0 to
+        // 1e16 rounds away entirely (1.0 is far below 1e16's ULP), so a
+        // naive left-to-right sum loses it completely once `-1e16` cancels
+        // the large term back out.
+        let data = vec![1e16, 1.0, -1e16];
+        let naive: f64 = data.iter().sum();
+        let compensated = neumaier_sum(&data);
+        assert_eq!(naive, 0.0, "naive sum should have dropped the 1.0 entirely");
+        assert_eq!(compensated, 1.0, "compensated sum should recover the 1.0");
+    }
+
+    #[test]
+    fn statistics_from_sorted_cfg_with_no_rounding_matches_raw_precision() {
+        let data = vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0];
+        let stats = statistics_from_sorted_cfg(&data, &StatConfig { round_decimals: None });
+        assert_eq!(stats.avg, data.iter().sum::<f64>() / data.len() as f64);
+    }
+
+    #[test]
+    fn statistics_from_sorted_cfg_rounds_every_field_uniformly() {
+        let data = vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0];
+        let stats = statistics_from_sorted_cfg(&data, &StatConfig { round_decimals: Some(1) });
+        let one_decimal = |x: f64| (x * 10.0).round() / 10.0 == x;
+        assert!(one_decimal(stats.avg));
+        assert!(one_decimal(stats.p50));
+        assert!(one_decimal(stats.stddev));
+    }
+
+    #[test]
+    fn statistics_from_sorted_cfg_is_nan_on_empty_input() {
+        let stats = statistics_from_sorted_cfg(&[], &StatConfig::default());
+        assert!(stats.avg.is_nan());
+        assert_eq!(stats.cnt, 0);
+    }
+
+    #[test]
+    fn quantile_interp_bridges_stored_percentiles() {
+        let data: Vec<f64> = (1..=1000).map(|i| i as f64).collect();
+        let stats = statistics_from_vec(data);
+        let p97 = stats.quantile_interp(0.97);
+        assert!(p97 > stats.p95 && p97 < stats.p99, "p97 was {p97}");
+        assert_eq!(stats.quantile_interp(0.5), stats.p50);
+    }
+
+    #[test]
+    fn quantile_interp_clamps_at_extremes() {
+        let stats = statistics_from_sorted(&[1.0, 2.0, 3.0]);
+        assert_eq!(stats.quantile_interp(-1.0), stats.min);
+        assert_eq!(stats.quantile_interp(2.0), stats.max);
+    }
+
+    #[test]
+    fn try_quantile_from_label_resolves_exact_stored_fields() {
+        let data: Vec<f64> = (1..=100_000).map(|i| i as f64).collect();
+        let stats = statistics_from_vec(data);
+        assert_eq!(stats.try_quantile_from_label("p50").unwrap(), stats.p50);
+        assert_eq!(stats.try_quantile_from_label("p99.9").unwrap(), stats.p999);
+        assert_eq!(stats.try_quantile_from_label("p99.99").unwrap(), stats.p9999);
+        assert_eq!(stats.try_quantile_from_label("median").unwrap(), stats.p50);
+        assert_eq!(stats.try_quantile_from_label("min").unwrap(), stats.min);
+        assert_eq!(stats.try_quantile_from_label("max").unwrap(), stats.max);
+        assert_eq!(stats.try_quantile_from_label("avg").unwrap(), stats.avg);
+        assert_eq!(stats.try_quantile_from_label("mean").unwrap(), stats.avg);
+    }
+
+    #[test]
+    fn try_quantile_from_label_is_case_insensitive_and_trims_whitespace() {
+        let stats = statistics_from_sorted(&[1.0, 2.0, 3.0]);
+        assert_eq!(
+            stats.try_quantile_from_label("P50").unwrap(),
+            stats.try_quantile_from_label(" p50 ").unwrap()
+        );
+    }
+
+    #[test]
+    fn try_quantile_from_label_falls_back_to_interpolation_for_unstored_percentiles() {
+        let data: Vec<f64> = (1..=1000).map(|i| i as f64).collect();
+        let stats = statistics_from_vec(data);
+        assert_eq!(stats.try_quantile_from_label("p97").unwrap(), stats.quantile_interp(0.97));
+    }
+
+    #[test]
+    fn try_quantile_from_label_rejects_unknown_labels_and_out_of_range_numbers() {
+        let stats = statistics_from_sorted(&[1.0, 2.0, 3.0]);
+        assert_eq!(stats.try_quantile_from_label("bogus"), Err(StatError::InvalidQuantile));
+        assert_eq!(stats.try_quantile_from_label("p150"), Err(StatError::InvalidQuantile));
+        assert_eq!(stats.try_quantile_from_label("p-1"), Err(StatError::InvalidQuantile));
+        assert_eq!(stats.try_quantile_from_label("p9.9.9"), Err(StatError::InvalidQuantile));
+    }
+
+    #[test]
+    fn filtered_drops_non_finite_samples() {
+        let data = vec![1.0, 2.0, f64::NAN, 3.0, f64::INFINITY, f64::NEG_INFINITY, 4.0];
+        let (stats, dropped) = statistics_from_vec_filtered(data);
+        assert_eq!(dropped, 3);
+        assert_eq!(stats.cnt, 4);
+        assert_eq!(stats.max, 4.0);
+    }
+
+    #[test]
+    fn online_stats_matches_batch_moments() {
+        let data: Vec<f64> = vec![2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        let mut online = OnlineStats::new();
+        for &x in &data {
+            online.insert(x);
+        }
+        let batch = statistics_from_vec(data.clone());
+        assert!((online.avg() - batch.avg).abs() < 1e-9);
+        assert!((online.variance() - batch.variance).abs() < 1e-9);
+        assert_eq!(online.min(), data.iter().cloned().fold(f64::INFINITY, f64::min));
+        assert_eq!(online.max(), batch.max);
+        assert_eq!(online.count(), batch.cnt);
+    }
+
+    #[test]
+    fn online_stats_empty_and_single() {
+        let empty = OnlineStats::new();
+        assert!(empty.avg().is_nan());
+        assert!(empty.variance().is_nan());
+
+        let mut single = OnlineStats::new();
+        single.insert(5.0);
+        assert_eq!(single.variance(), 0.0);
+        assert_eq!(single.avg(), 5.0);
+    }
+
+    #[test]
+    fn online_stats_to_statistics_matches_the_moments_with_nan_percentiles() {
+        let data: Vec<f64> = vec![2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        let mut online = OnlineStats::new();
+        for &x in &data {
+            online.insert(x);
+        }
+        let stats = online.to_statistics();
+        assert_eq!(stats.cnt, data.len());
+        assert!((stats.avg - online.avg()).abs() < 1e-9);
+        assert_eq!(stats.variance, online.variance());
+        assert_eq!(stats.min, online.min());
+        assert_eq!(stats.max, online.max());
+        assert!(stats.p50.is_nan());
+        assert!(stats.p99.is_nan());
+    }
+
+    #[test]
+    fn online_stats_to_statistics_is_empty_on_no_samples() {
+        let stats = OnlineStats::new().to_statistics();
+        assert_eq!(stats.cnt, 0);
+        assert!(stats.avg.is_nan());
+        assert!(stats.sum.is_nan());
+    }
+
+    #[test]
+    fn min_max_tracks_the_range_of_a_stream() {
+        let data = vec![5.0, 2.0, 9.0, 4.0, 7.0];
+        let mut mm = MinMax::new();
+        for &x in &data {
+            mm.insert(x);
+        }
+        assert_eq!(mm.min(), 2.0);
+        assert_eq!(mm.max(), 9.0);
+        assert_eq!(mm.range(), 7.0);
+        assert_eq!(mm.count(), data.len());
+    }
+
+    #[test]
+    fn min_max_ignores_nan_and_is_nan_when_empty() {
+        let empty = MinMax::new();
+        assert!(empty.min().is_nan());
+        assert!(empty.max().is_nan());
+        assert!(empty.range().is_nan());
+        assert_eq!(empty.count(), 0);
+
+        let mut mm = MinMax::new();
+        mm.insert(f64::NAN);
+        mm.insert(3.0);
+        mm.insert(f64::NAN);
+        assert_eq!(mm.min(), 3.0);
+        assert_eq!(mm.max(), 3.0);
+        assert_eq!(mm.count(), 1);
+    }
+
+    #[test]
+    fn statistics_from_iter_matches_from_vec() {
+        let iter_stats = statistics_from_iter((1..=500).map(|i| i as f64));
+        let vec_stats = statistics_from_vec((1..=500).map(|i| i as f64).collect());
+        assert_eq!(iter_stats.p50, vec_stats.p50);
+        assert_eq!(iter_stats.cnt, vec_stats.cnt);
+    }
+
+    #[test]
+    fn statistics_approx_from_iter_is_close_to_exact() {
+        let approx = statistics_approx_from_iter((1..=10_000).map(|i| i as f64));
+        let exact = statistics_from_vec((1..=10_000).map(|i| i as f64).collect());
+        assert!((approx.p99 - exact.p99).abs() < exact.p99 * 0.05);
+    }
+
+    #[test]
+    fn custom_statistics_computes_only_requested_quantiles() {
+        let data: Vec<f64> = (1..=1000).map(|i| i as f64).collect();
+        let custom = statistics_from_sorted_with(&data, &[0.5, 0.99]);
+        assert_eq!(custom.cnt, 1000);
+        assert_eq!(custom.quantiles.len(), 2);
+        let full = statistics_from_sorted(&data);
+        assert_eq!(custom.quantiles["0.5"], full.p50);
+        assert_eq!(custom.quantiles["0.99"], full.p99);
+    }
+
+    #[test]
+    fn display_shows_na_for_nan_fields() {
+        let empty = statistics_from_sorted(&[]);
+        let rendered = empty.to_string();
+        assert!(rendered.contains("avg:    n/a"));
+        assert!(rendered.contains("cnt:    0"));
+    }
+
+    #[test]
+    fn display_formats_known_values() {
+        let stats = statistics_from_sorted(&[1.0, 2.0, 3.0]);
+        let rendered = stats.to_string();
+        assert!(rendered.contains("min:    1.00"));
+        assert!(rendered.contains("max:    3.00"));
+    }
+
+    #[test]
+    fn diff_reports_delta_and_percentage() {
+        let baseline = statistics_from_sorted(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+        let current = statistics_from_sorted(&[2.0, 4.0, 6.0, 8.0, 10.0]);
+        let d = current.diff(&baseline);
+        assert!((d.max.0 - 5.0).abs() < 1e-9);
+        assert!((d.max.1.unwrap() - 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn diff_percentage_is_none_for_zero_or_nan_baseline() {
+        let baseline = statistics_from_sorted(&[]);
+        let current = statistics_from_sorted(&[1.0, 2.0, 3.0]);
+        let d = current.diff(&baseline);
+        assert_eq!(d.avg.1, None);
+    }
+
+    #[test]
+    fn regressed_lists_fields_beyond_threshold() {
+        let baseline = statistics_from_sorted(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+        let current = statistics_from_sorted(&[2.0, 4.0, 6.0, 8.0, 10.0]);
+        let regressions = current.regressed(&baseline, 50.0);
+        assert!(regressions.contains(&"max".to_string()));
+        assert!(current.regressed(&baseline, 200.0).is_empty());
+    }
+
+    #[test]
+    fn regression_report_fails_when_a_tighter_threshold_is_tripped() {
+        let baseline = statistics_from_sorted(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+        let current = statistics_from_sorted(&[2.0, 4.0, 6.0, 8.0, 10.0]);
+        let thresholds = RegressionThresholds {
+            p50: Some(10.0),
+            p999: Some(1000.0),
+            ..Default::default()
+        };
+        let report = regression_report(&baseline, &current, &thresholds);
+        assert!(!report.passed);
+        assert!(report.regressions.contains(&"p50".to_string()));
+        assert!(!report.regressions.contains(&"max".to_string()), "max has no threshold set");
+    }
+
+    #[test]
+    fn regression_report_passes_when_every_threshold_is_wide_enough() {
+        let baseline = statistics_from_sorted(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+        let current = statistics_from_sorted(&[2.0, 4.0, 6.0, 8.0, 10.0]);
+        let thresholds = RegressionThresholds {
+            p50: Some(1000.0),
+            max: Some(1000.0),
+            ..Default::default()
+        };
+        let report = regression_report(&baseline, &current, &thresholds);
+        assert!(report.passed);
+        assert!(report.regressions.is_empty());
+    }
+
+    #[test]
+    fn regression_report_ignores_fields_with_no_threshold() {
+        let baseline = statistics_from_sorted(&[1.0, 2.0, 3.0]);
+        let current = statistics_from_sorted(&[100.0, 200.0, 300.0]);
+        let report = regression_report(&baseline, &current, &RegressionThresholds::default());
+        assert!(report.passed);
+        assert!(report.regressions.is_empty());
+    }
+
+    #[test]
+    fn regression_report_to_string_renders_pass_fail_and_flags() {
+        let baseline = statistics_from_sorted(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+        let current = statistics_from_sorted(&[2.0, 4.0, 6.0, 8.0, 10.0]);
+        let thresholds = RegressionThresholds { max: Some(10.0), ..Default::default() };
+        let report = regression_report(&baseline, &current, &thresholds);
+        let rendered = report.to_string();
+        assert!(rendered.contains("FAIL"));
+        assert!(rendered.contains("max"));
+        assert!(rendered.contains("[REGRESSED]"));
+    }
+
+    #[test]
+    fn trimmed_mean_drops_extreme_tails() {
+        let mut data: Vec<f64> = (1..=100).map(|i| i as f64).collect();
+        data.push(100_000.0);
+        let trimmed = trimmed_mean(&data, 0.05);
+        let plain_avg: f64 = data.iter().sum::<f64>() / (data.len() as f64);
+        assert!(trimmed < plain_avg, "trimmed {trimmed} should drop the outlier's pull");
+    }
+
+    #[test]
+    fn trimmed_mean_matches_plain_mean_with_zero_trim() {
+        let data = vec![1.0, 2.0, 3.0, 4.0, 5.0];
+        assert!((trimmed_mean(&data, 0.0) - 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn winsorized_mean_clamps_rather_than_drops() {
+        let mut data: Vec<f64> = (1..=100).map(|i| i as f64).collect();
+        data.push(100_000.0);
+        let winsorized = winsorized_mean(&data, 0.05);
+        let trimmed = trimmed_mean(&data, 0.05);
+        // Winsorizing still counts every sample, so the count-normalized mean
+        // differs slightly from dropping them outright, but both resist the
+        // outlier far more than the plain average does.
+        assert!(winsorized.is_finite() && trimmed.is_finite());
+    }
+
+    #[test]
+    fn trimmed_and_winsorized_are_nan_on_empty_input() {
+        assert!(trimmed_mean(&[], 0.1).is_nan());
+        assert!(winsorized_mean(&[], 0.1).is_nan());
+    }
+
+    #[test]
+    fn iqr_matches_known_percentiles() {
+        let data: Vec<f64> = (1..=100).map(|i| i as f64).collect();
+        let spread = iqr(&data);
+        assert!((spread - 49.5).abs() < 1e-9, "iqr was {spread}");
+    }
+
+    #[test]
+    fn iqr_is_nan_on_empty_input() {
+        assert!(iqr(&[]).is_nan());
+    }
+
+    #[test]
+    fn outliers_flags_values_beyond_tukey_fences() {
+        let mut data: Vec<f64> = (1..=100).map(|i| i as f64).collect();
+        data.push(10_000.0);
+        let (low, high) = outliers(&data, 1.5);
+        assert!(low.is_empty());
+        assert_eq!(high, vec![10_000.0]);
+    }
+
+    #[test]
+    fn outliers_is_empty_without_tail_values() {
+        let data: Vec<f64> = (1..=100).map(|i| i as f64).collect();
+        let (low, high) = outliers(&data, 1.5);
+        assert!(low.is_empty() && high.is_empty());
+    }
+
+    #[test]
+    fn outliers_separates_low_and_high_tails() {
+        let mut data: Vec<f64> = (1..=100).map(|i| i as f64).collect();
+        data.push(-10_000.0);
+        data.push(10_000.0);
+        let (low, high) = outliers(&data, 1.5);
+        assert_eq!(low, vec![-10_000.0]);
+        assert_eq!(high, vec![10_000.0]);
+    }
+
+    #[test]
+    fn value_counts_groups_by_exact_equality_sorted_by_value() {
+        let data = vec![3.0, 1.0, 3.0, 2.0, 1.0, 1.0];
+        assert_eq!(value_counts(&data), vec![(1.0, 3), (2.0, 1), (3.0, 2)]);
+    }
+
+    #[test]
+    fn value_counts_excludes_nan() {
+        let data = vec![1.0, f64::NAN, 1.0, f64::NAN];
+        assert_eq!(value_counts(&data), vec![(1.0, 2)]);
+    }
+
+    #[test]
+    fn mode_returns_most_frequent_value() {
+        let data = vec![5.0, 5.0, 1.0, 2.0, 2.0, 2.0];
+        assert_eq!(mode(&data), Some((2.0, 3)));
+    }
+
+    #[test]
+    fn mode_breaks_ties_by_smallest_value() {
+        let data = vec![5.0, 5.0, 1.0, 1.0];
+        assert_eq!(mode(&data), Some((1.0, 2)));
+    }
+
+    #[test]
+    fn mode_is_none_on_empty_input() {
+        assert_eq!(mode(&[]), None);
+    }
+
+    #[test]
+    fn tail_ratios_compare_tail_to_median() {
+        let data: Vec<f64> = (1..=1000).map(|i| i as f64).collect();
+        let stats = statistics_from_sorted(&data);
+        assert!((stats.tail_ratio_99() - stats.p99 / stats.p50).abs() < 1e-9);
+        assert!((stats.tail_ratio_999() - stats.p999 / stats.p50).abs() < 1e-9);
+        assert!((stats.ratio(stats.p999, stats.p95) - stats.p999 / stats.p95).abs() < 1e-9);
+    }
+
+    #[test]
+    fn tail_ratios_are_nan_not_inf_on_zero_or_nan_median() {
+        let empty = statistics_from_sorted(&[]);
+        assert!(empty.tail_ratio_99().is_nan());
+
+        let data = vec![0.0, 0.0, 0.0, 1.0];
+        let stats = statistics_from_sorted(&data);
+        assert_eq!(stats.p50, 0.0);
+        assert!(stats.tail_ratio_99().is_nan());
+    }
+
+    #[test]
+    fn coefficient_of_variation_matches_stddev_over_avg() {
+        let data = vec![2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        let stats = statistics_from_sorted(&data);
+        assert!((stats.coefficient_of_variation() - stats.stddev / stats.avg).abs() < 1e-9);
+        assert!((stats.rsd_pct() - stats.coefficient_of_variation() * 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn coefficient_of_variation_is_nan_on_zero_or_nan_avg() {
+        let empty = statistics_from_sorted(&[]);
+        assert!(empty.coefficient_of_variation().is_nan());
+        assert!(empty.rsd_pct().is_nan());
+
+        let data = vec![0.0, 0.0, 0.0];
+        let stats = statistics_from_sorted(&data);
+        assert_eq!(stats.avg, 0.0);
+        assert!(stats.coefficient_of_variation().is_nan());
+    }
+
+    #[test]
+    fn iqr_matches_p75_minus_p25() {
+        let data: Vec<f64> = (1..=1000).map(|i| i as f64).collect();
+        let stats = statistics_from_sorted(&data);
+        assert!((stats.iqr() - (stats.p75 - stats.p25)).abs() < 1e-9);
+        assert!(stats.iqr() > 0.0);
+    }
+
+    #[test]
+    fn statistics_iqr_is_nan_on_empty_input() {
+        let empty = statistics_from_sorted(&[]);
+        assert!(empty.iqr().is_nan());
+    }
+
+    #[test]
+    fn percentiles_yields_every_field_in_ascending_rank_order() {
+        let data: Vec<f64> = (1..=1000).map(|i| i as f64).collect();
+        let stats = statistics_from_sorted(&data);
+        let names: Vec<&str> = stats.percentiles().map(|(name, _)| name).collect();
+        assert_eq!(
+            names,
+            vec!["p1", "p5", "p10", "p25", "p30", "p50", "p75", "p80", "p90", "p95", "p99", "p999", "p9999"]
+        );
+        let values: HashMap<&str, f64> = stats.percentiles().collect();
+        assert_eq!(values["p50"], stats.p50);
+        assert_eq!(values["p99"], stats.p99);
+    }
+
+    #[test]
+    fn percentile_map_matches_percentiles_iterator() {
+        let stats = statistics_from_sorted(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+        let map = stats.percentile_map();
+        assert_eq!(map.len(), 13);
+        for (name, value) in stats.percentiles() {
+            assert_eq!(map[name], value);
+        }
+    }
+
+    #[test]
+    fn geometric_mean_matches_known_value() {
+        let data = vec![1.0, 4.0, 16.0];
+        // geometric mean of 1, 4, 16 is (1*4*16)^(1/3) = 4.
+        assert!((geometric_mean(&data) - 4.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn harmonic_mean_matches_known_value() {
+        let data = vec![1.0, 2.0, 4.0];
+        // harmonic mean = 3 / (1 + 0.5 + 0.25) = 1.714285...
+        assert!((harmonic_mean(&data) - 12.0 / 7.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn geometric_and_harmonic_mean_are_nan_on_non_positive_input() {
+        assert!(geometric_mean(&[]).is_nan());
+        assert!(harmonic_mean(&[]).is_nan());
+        assert!(geometric_mean(&[1.0, 0.0, 2.0]).is_nan());
+        assert!(harmonic_mean(&[1.0, -1.0, 2.0]).is_nan());
+    }
+
+    #[test]
+    fn skewness_is_positive_for_right_skewed_data() {
+        let mut data: Vec<f64> = (1..=100).map(|i| i as f64).collect();
+        data.push(10_000.0);
+        assert!(skewness(&data) > 0.0);
+    }
+
+    #[test]
+    fn skewness_is_near_zero_for_symmetric_data() {
+        let data = vec![1.0, 2.0, 3.0, 4.0, 5.0];
+        assert!(skewness(&data).abs() < 1e-9);
+    }
+
+    #[test]
+    fn kurtosis_is_positive_for_heavy_tailed_data() {
+        let mut data: Vec<f64> = (1..=200).map(|i| i as f64).collect();
+        data.push(1_000_000.0);
+        assert!(kurtosis(&data) > 0.0);
+    }
+
+    #[test]
+    fn skewness_and_kurtosis_are_nan_on_too_few_samples() {
+        assert!(skewness(&[1.0, 2.0]).is_nan());
+        assert!(kurtosis(&[1.0, 2.0, 3.0]).is_nan());
+    }
+
+    #[test]
+    fn bimodality_coefficient_is_high_for_two_well_separated_clusters() {
+        let mut data: Vec<f64> = (0..50).map(|i| i as f64).collect();
+        data.extend((0..50).map(|i| 1_000.0 + i as f64));
+        assert!(is_likely_bimodal(&data), "coefficient was {}", bimodality_coefficient(&data));
+    }
+
+    #[test]
+    fn bimodality_coefficient_is_low_for_a_single_sharp_peak() {
+        let mut data = vec![5.0; 90];
+        data.extend([1.0, 2.0, 3.0, 4.0, 6.0, 7.0, 8.0, 9.0]);
+        assert!(!is_likely_bimodal(&data), "coefficient was {}", bimodality_coefficient(&data));
+    }
+
+    #[test]
+    fn bimodality_coefficient_is_nan_on_too_few_samples() {
+        assert!(bimodality_coefficient(&[1.0, 2.0, 3.0]).is_nan());
+    }
+
+    #[test]
+    fn median_absolute_deviation_matches_known_value() {
+        let data = vec![1.0, 1.0, 2.0, 2.0, 4.0, 6.0, 9.0];
+        // median is 2.0; deviations are [1, 1, 0, 0, 2, 4, 7], median 1.0.
+        assert!((median_absolute_deviation(&data) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn mad_normal_scales_mad_by_consistency_constant() {
+        let data = vec![1.0, 1.0, 2.0, 2.0, 4.0, 6.0, 9.0];
+        assert!((mad_normal(&data) - median_absolute_deviation(&data) * 1.4826).abs() < 1e-9);
+    }
+
+    #[test]
+    fn mad_is_robust_to_a_single_extreme_outlier() {
+        let mut data: Vec<f64> = (1..=99).map(|i| i as f64).collect();
+        data.push(1_000_000.0);
+        let mad = median_absolute_deviation(&data);
+        assert!(mad < 100.0, "mad was {mad}");
+    }
+
+    #[test]
+    fn median_absolute_deviation_is_nan_on_empty_input() {
+        assert!(median_absolute_deviation(&[]).is_nan());
+    }
+
+    #[test]
+    fn try_statistics_from_sorted_matches_statistics_from_sorted_on_valid_input() {
+        let data: Vec<f64> = (1..=100).map(|i| i as f64).collect();
+        let tried = try_statistics_from_sorted(&data).unwrap();
+        let plain = statistics_from_sorted(&data);
+        assert_eq!(tried.p50, plain.p50);
+        assert_eq!(tried.cnt, plain.cnt);
+    }
+
+    #[test]
+    fn try_statistics_from_sorted_rejects_empty_input() {
+        assert!(matches!(try_statistics_from_sorted(&[]), Err(StatError::Empty)));
+    }
+
+    #[test]
+    fn statistics_from_sorted_checked_matches_statistics_from_sorted_on_valid_input() {
+        let data: Vec<f64> = (1..=100).map(|i| i as f64).collect();
+        let checked = statistics_from_sorted_checked(&data).unwrap();
+        let plain = statistics_from_sorted(&data);
+        assert_eq!(checked.p50, plain.p50);
+        assert_eq!(checked.cnt, plain.cnt);
+    }
+
+    #[test]
+    fn statistics_from_sorted_checked_rejects_unsorted_input() {
+        let data = [1.0, 3.0, 2.0, 4.0];
+        assert!(matches!(statistics_from_sorted_checked(&data), Err(StatError::NotSorted)));
+    }
+
+    #[test]
+    fn statistics_from_sorted_checked_accepts_empty_input() {
+        assert!(statistics_from_sorted_checked(&[]).unwrap().cnt == 0);
+    }
+
+    #[test]
+    fn is_sorted_non_decreasing_ignores_nan_placement() {
+        assert!(is_sorted_non_decreasing(&[1.0, f64::NAN, 2.0, 3.0]));
+        assert!(is_sorted_non_decreasing(&[f64::NAN, 1.0, 2.0]));
+        assert!(!is_sorted_non_decreasing(&[1.0, 3.0, 2.0]));
+    }
+
+    #[test]
+    fn statistics_to_map_round_trips_through_f64_from_stat() {
+        let stats = statistics_from_sorted(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+        let map = statistics_to_map(&stats);
+        assert_eq!(f64_from_stat(&map, "p50"), Some(stats.p50));
+        assert_eq!(f64_from_stat(&map, "max"), Some(stats.max));
+        assert_eq!(map.get("cnt").and_then(|v| v.as_u64()), Some(stats.cnt as u64));
+    }
+
+    #[test]
+    fn statistics_to_map_emits_null_for_nan_fields() {
+        let empty = statistics_from_sorted(&[]);
+        let map = statistics_to_map(&empty);
+        assert_eq!(map.get("avg"), Some(&serde_json::Value::Null));
+        assert_eq!(f64_from_stat(&map, "avg"), None);
+    }
+
+    #[test]
+    fn f64_from_stat_parses_string_encoded_numbers() {
+        let map = HashMap::from([("p99".to_string(), serde_json::json!("123.4"))]);
+        assert_eq!(f64_from_stat(&map, "p99"), Some(123.4));
+    }
+
+    #[test]
+    fn f64_from_stat_path_resolves_nested_and_string_leaves() {
+        let map: HashMap<String, serde_json::Value> = serde_json::from_str(
+            r#"{"latency": {"p99": "9900.5", "p50": 500}}"#,
+        )
+        .unwrap();
+        assert_eq!(f64_from_stat_path(&map, "latency.p99"), Some(9900.5));
+        assert_eq!(f64_from_stat_path(&map, "latency.p50"), Some(500.0));
+        assert_eq!(f64_from_stat_path(&map, "latency.missing"), None);
+        assert_eq!(f64_from_stat_path(&map, "missing.p99"), None);
+        assert_eq!(f64_from_stat_path(&map, "latency.p99.deeper"), None);
+    }
+
+    #[test]
+    fn aggregate_from_stat_maps_rolls_up_present_keys() {
+        let maps: Vec<HashMap<String, serde_json::Value>> = (1..=5)
+            .map(|i| HashMap::from([("p99".to_string(), serde_json::json!(i as f64 * 100.0))]))
+            .collect();
+        let (stats, missing) = aggregate_from_stat_maps(&maps, "p99");
+        assert_eq!(missing, 0);
+        assert_eq!(stats.cnt, 5);
+        assert_eq!(stats.max, 500.0);
+    }
+
+    #[test]
+    fn aggregate_from_stat_maps_counts_missing_keys() {
+        let maps = vec![
+            HashMap::from([("p99".to_string(), serde_json::json!(100.0))]),
+            HashMap::from([("p50".to_string(), serde_json::json!(50.0))]),
+            HashMap::from([("p99".to_string(), serde_json::json!("not a number"))]),
+        ];
+        let (stats, missing) = aggregate_from_stat_maps(&maps, "p99");
+        assert_eq!(missing, 2);
+        assert_eq!(stats.cnt, 1);
+        assert_eq!(stats.max, 100.0);
+    }
+
+    #[test]
+    fn merge_statistics_weights_by_count_and_takes_true_extremes() {
+        let a = statistics_from_sorted(&[1.0, 2.0, 3.0]);
+        let b = statistics_from_sorted(&[10.0, 20.0, 30.0, 40.0, 50.0, 60.0]);
+        let merged = merge_statistics(&[a.clone(), b.clone()]);
+        assert_eq!(merged.cnt, a.cnt + b.cnt);
+        assert_eq!(merged.min, 1.0);
+        assert_eq!(merged.max, 60.0);
+        let expected_avg = (a.avg * a.cnt as f64 + b.avg * b.cnt as f64) / merged.cnt as f64;
+        assert!((merged.avg - expected_avg).abs() < 1e-9);
+    }
+
+    #[test]
+    fn merge_statistics_ignores_empty_parts() {
+        let a = statistics_from_sorted(&[1.0, 2.0, 3.0]);
+        let empty = statistics_from_sorted(&[]);
+        let merged = merge_statistics(&[a.clone(), empty]);
+        assert_eq!(merged.cnt, a.cnt);
+        assert!((merged.avg - a.avg).abs() < 1e-9);
+        assert!(!merged.avg.is_nan());
+    }
+
+    #[test]
+    fn merge_statistics_of_all_empty_parts_is_empty() {
+        let merged = merge_statistics(&[statistics_from_sorted(&[]), statistics_from_sorted(&[])]);
+        assert_eq!(merged.cnt, 0);
+        assert!(merged.avg.is_nan());
+        assert!(merged.sum.is_nan());
+    }
+
+    #[test]
+    fn statistics_nearest_rank_diverges_from_interpolated_on_p90() {
+        let data = [1.0, 2.0, 3.0, 4.0, 5.0];
+        let interpolated = statistics_from_sorted(&data);
+        let nearest_rank = statistics_nearest_rank(&data);
+        assert!((interpolated.p90 - 4.6).abs() < 1e-9, "p90 {}", interpolated.p90);
+        assert_eq!(nearest_rank.p90, 5.0);
+        assert_ne!(interpolated.p90, nearest_rank.p90);
+    }
+
+    #[test]
+    fn statistics_nearest_rank_always_returns_an_actual_sample() {
+        let data = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0];
+        let stats = statistics_nearest_rank(&data);
+        for p in [stats.p1, stats.p5, stats.p10, stats.p25, stats.p30, stats.p50, stats.p75, stats.p80, stats.p90, stats.p95, stats.p99, stats.p999, stats.p9999] {
+            assert!(data.contains(&p), "{p} is not one of the raw samples");
+        }
+    }
+
+    #[test]
+    fn statistics_nearest_rank_shares_avg_stddev_and_extremes_with_the_interpolated_version() {
+        let data = [1.0, 2.0, 3.0, 4.0, 5.0];
+        let interpolated = statistics_from_sorted(&data);
+        let nearest_rank = statistics_nearest_rank(&data);
+        assert_eq!(interpolated.avg, nearest_rank.avg);
+        assert_eq!(interpolated.sum, nearest_rank.sum);
+        assert_eq!(interpolated.stddev, nearest_rank.stddev);
+        assert_eq!(interpolated.min, nearest_rank.min);
+        assert_eq!(interpolated.max, nearest_rank.max);
+        assert_eq!(interpolated.cnt, nearest_rank.cnt);
+    }
+
+    #[test]
+    fn statistics_nearest_rank_is_empty_on_empty_data() {
+        let stats = statistics_nearest_rank(&[]);
+        assert_eq!(stats.cnt, 0);
+        assert!(stats.p50.is_nan());
+    }
+
+    #[test]
+    fn statistics_from_sorted_sum_matches_avg_times_cnt() {
+        let stats = statistics_from_sorted(&[1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(stats.sum, 10.0);
+        assert!((stats.sum / stats.cnt as f64 - stats.avg).abs() < 1e-9);
+    }
+
+    #[test]
+    fn statistics_from_sorted_sum_is_nan_on_empty_data() {
+        let stats = statistics_from_sorted(&[]);
+        assert!(stats.sum.is_nan());
+    }
+
+    #[test]
+    fn merge_statistics_sum_is_the_exact_total_of_the_parts_sums() {
+        let a = statistics_from_sorted(&[1.0, 2.0, 3.0]);
+        let b = statistics_from_sorted(&[10.0, 20.0, 30.0, 40.0, 50.0, 60.0]);
+        let merged = merge_statistics(&[a.clone(), b.clone()]);
+        assert_eq!(merged.sum, a.sum + b.sum);
+    }
+
+    /// `avg` on already-rounded parts is a rounding of a rounding, but
+    /// `merge_statistics` re-derives it from the exact summed `sum` rather
+    /// than weight-averaging the parts' own (already-rounded) `avg` fields,
+    /// so the merged `avg` can be closer to the true grand mean than a
+    /// naive weighted average of `avg`s would be.
+    #[test]
+    fn merge_statistics_avg_is_derived_from_the_exact_summed_total() {
+        let a = statistics_from_sorted(&[1.0, 2.0, 3.0]);
+        let b = statistics_from_sorted(&[10.0, 20.0, 30.0, 40.0, 50.0, 60.0]);
+        let merged = merge_statistics(&[a.clone(), b.clone()]);
+        let expected_avg = ((a.sum + b.sum) / merged.cnt as f64 * 100.0).round() / 100.0;
+        assert_eq!(merged.avg, expected_avg);
+    }
+
+    #[test]
+    fn grouped_statistics_buckets_by_key() {
+        let samples = vec![
+            ("get", 1.0),
+            ("get", 2.0),
+            ("get", 3.0),
+            ("send", 100.0),
+            ("send", 200.0),
+        ];
+        let groups = grouped_statistics(samples);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups["get"].cnt, 3);
+        assert_eq!(groups["send"].cnt, 2);
+        assert_eq!(groups["send"].min, 100.0);
+    }
+
+    #[test]
+    fn grouped_statistics_is_empty_on_no_samples() {
+        let groups: HashMap<&str, Statistics> = grouped_statistics(std::iter::empty());
+        assert!(groups.is_empty());
+    }
+
+    #[test]
+    fn overall_statistics_matches_merge_statistics_of_the_groups() {
+        let samples = vec![("get", 1.0), ("get", 2.0), ("send", 100.0)];
+        let groups = grouped_statistics(samples);
+        let overall = overall_statistics(&groups);
+        assert_eq!(overall.cnt, 3);
+        assert_eq!(overall.min, 1.0);
+        assert_eq!(overall.max, 100.0);
+    }
+
+    #[test]
+    fn to_prometheus_emits_count_sum_and_quantile_lines() {
+        let stats = statistics_from_sorted(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+        let text = to_prometheus(&stats, "latency_ns", &[("service", "foo")]);
+        assert!(text.contains(r#"latency_ns_count{service="foo"} 5"#));
+        assert!(text.contains(&format!(r#"latency_ns_sum{{service="foo"}} {}"#, stats.avg * 5.0)));
+        assert!(text.contains(&format!(
+            r#"latency_ns{{service="foo",quantile="0.99"}} {}"#,
+            stats.p99
+        )));
+        assert!(!text.contains("NaN"));
+    }
+
+    #[test]
+    fn to_prometheus_omits_nan_fields_and_labels_when_empty() {
+        let empty = statistics_from_sorted(&[]);
+        let text = to_prometheus(&empty, "latency_ns", &[]);
+        assert!(text.contains("latency_ns_count 0"));
+        assert!(!text.contains("latency_ns_sum"));
+        assert!(!text.contains("quantile"));
+        assert!(!text.contains("NaN"));
+    }
+
+    #[test]
+    fn csv_header_and_row_have_matching_column_counts() {
+        let stats = statistics_from_sorted(&[1.0, 2.0, 3.0]);
+        let header_cols = csv_header().split(',').count();
+        let row_cols = to_csv_row(&stats).split(',').count();
+        assert_eq!(header_cols, row_cols);
+    }
+
+    #[test]
+    fn to_csv_row_renders_nan_as_empty_and_floats_fixed() {
+        let empty = statistics_from_sorted(&[]);
+        let row = to_csv_row(&empty);
+        assert_eq!(row, ",,,,,,,,,,,,,,,,,,0");
+
+        let stats = statistics_from_sorted(&[1.0, 2.0, 3.0]);
+        assert!(to_csv_row(&stats).contains("2.00"));
+    }
+
+    #[test]
+    fn to_csv_row_fmt_respects_a_custom_decimal_count() {
+        let stats = statistics_from_sorted(&[1.0, 2.0, 3.0]);
+        assert!(to_csv_row_fmt(&stats, FloatFmt::decimals(0)).starts_with("2,"));
+        assert_eq!(to_csv_row(&stats), to_csv_row_fmt(&stats, FloatFmt::TWO_DECIMALS));
+    }
+
+    #[test]
+    fn to_prometheus_fmt_rounds_to_the_requested_precision() {
+        let stats = statistics_from_sorted(&[1.0, 2.0, 3.0, 4.0]);
+        let full = to_prometheus_fmt(&stats, "latency_ns", &[], FloatFmt::FULL);
+        let rounded = to_prometheus_fmt(&stats, "latency_ns", &[], FloatFmt::decimals(1));
+        assert_eq!(full, to_prometheus(&stats, "latency_ns", &[]));
+        assert!(rounded.contains(&format!("latency_ns_sum {:.1}\n", stats.sum)));
+        assert_ne!(full, rounded);
+    }
+
+    #[test]
+    fn statistics_render_respects_a_custom_decimal_count() {
+        let stats = statistics_from_sorted(&[1.0, 2.0, 3.0]);
+        assert!(stats.render(FloatFmt::decimals(0)).contains("min:    1\n"));
+        assert_eq!(stats.render(FloatFmt::TWO_DECIMALS), stats.to_string());
+    }
+
+    #[test]
+    fn percentile_timeseries_csv_has_a_column_per_requested_percentile() {
+        let snapshots = vec![
+            (0.0, statistics_from_sorted(&(1..=100).map(|i| i as f64).collect::<Vec<_>>())),
+            (10.0, statistics_from_sorted(&(1..=200).map(|i| i as f64).collect::<Vec<_>>())),
+        ];
+        let csv = percentile_timeseries_csv(&snapshots, &["p50", "p99"]);
+        let mut lines = csv.lines();
+        assert_eq!(lines.next().unwrap(), "timestamp,p50,p99");
+        assert_eq!(lines.next().unwrap().split(',').count(), 3);
+        assert_eq!(lines.next().unwrap().split(',').count(), 3);
+        assert!(lines.next().is_none());
+    }
+
+    #[test]
+    fn percentile_timeseries_csv_renders_nan_percentiles_as_empty() {
+        let snapshots = vec![(0.0, statistics_from_sorted(&[]))];
+        let csv = percentile_timeseries_csv(&snapshots, &["p99"]);
+        assert_eq!(csv, "timestamp,p99\n0,\n");
+    }
+
+    #[test]
+    fn ks_two_sample_is_near_zero_for_identical_distributions() {
+        let data: Vec<f64> = (1..=1000).map(|i| i as f64).collect();
+        let (d, p) = ks_two_sample(&data, &data);
+        assert!(d < 1e-9, "D was {d}");
+        assert!(p > 0.99, "p was {p}");
+    }
+
+    #[test]
+    fn ks_two_sample_detects_shifted_distributions() {
+        let a: Vec<f64> = (1..=1000).map(|i| i as f64).collect();
+        let b: Vec<f64> = (1..=1000).map(|i| i as f64 + 5000.0).collect();
+        let (d, p) = ks_two_sample(&a, &b);
+        assert!((d - 1.0).abs() < 1e-9, "D was {d}");
+        assert!(p < 0.01, "p was {p}");
+    }
+
+    #[test]
+    fn ks_two_sample_is_nan_on_empty_input() {
+        let (d, p) = ks_two_sample(&[], &[1.0, 2.0]);
+        assert!(d.is_nan() && p.is_nan());
+    }
+
+    #[test]
+    fn mann_whitney_u_is_high_p_for_identical_distributions() {
+        let a: Vec<f64> = (1..=200).map(|i| i as f64).collect();
+        let b: Vec<f64> = (1..=200).map(|i| i as f64).collect();
+        let (_, p) = mann_whitney_u(&a, &b);
+        assert!(p > 0.9, "p was {p}");
+    }
+
+    #[test]
+    fn mann_whitney_u_detects_shifted_distributions() {
+        let a: Vec<f64> = (1..=200).map(|i| i as f64).collect();
+        let b: Vec<f64> = (1..=200).map(|i| i as f64 + 1000.0).collect();
+        let (_, p) = mann_whitney_u(&a, &b);
+        assert!(p < 0.01, "p was {p}");
+    }
+
+    #[test]
+    fn mann_whitney_u_is_nan_on_empty_input() {
+        let (u, p) = mann_whitney_u(&[], &[1.0]);
+        assert!(u.is_nan() && p.is_nan());
+    }
+
+    #[test]
+    fn bootstrap_ci_brackets_the_true_quantile() {
+        let data: Vec<f64> = (1..=1000).map(|i| i as f64).collect();
+        let (lo, hi) = bootstrap_ci(&data, 0.5, 500, 0.05, 42);
+        assert!(lo <= 500.5 && hi >= 500.5, "CI was [{lo}, {hi}]");
+        assert!(lo < hi);
+    }
+
+    #[test]
+    fn bootstrap_ci_is_deterministic_for_a_fixed_seed() {
+        let data: Vec<f64> = (1..=200).map(|i| i as f64).collect();
+        let a = bootstrap_ci(&data, 0.99, 200, 0.1, 7);
+        let b = bootstrap_ci(&data, 0.99, 200, 0.1, 7);
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn bootstrap_ci_is_nan_on_empty_input() {
+        let (lo, hi) = bootstrap_ci(&[], 0.5, 100, 0.05, 1);
+        assert!(lo.is_nan() && hi.is_nan());
+    }
+
+    #[test]
+    fn cdf_points_spans_zero_to_one_with_matching_quantiles() {
+        let data: Vec<f64> = (1..=100).map(|i| i as f64).collect();
+        let points = cdf_points(&data, 5);
+        let probs: Vec<f64> = points.iter().map(|&(_, p)| p).collect();
+        assert_eq!(probs, vec![0.0, 0.25, 0.5, 0.75, 1.0]);
+        assert_eq!(points[0].0, exact_quantile(&data, 0.0, QuantileInterpolation::Linear));
+        assert_eq!(points[4].0, exact_quantile(&data, 1.0, QuantileInterpolation::Linear));
+    }
+
+    #[test]
+    fn cdf_points_is_empty_on_empty_data_or_too_few_points() {
+        assert!(cdf_points(&[], 10).is_empty());
+        assert!(cdf_points(&[1.0, 2.0], 1).is_empty());
+    }
+
+    #[test]
+    fn log_histogram_buckets_span_orders_of_magnitude() {
+        let data = vec![1.0, 5.0, 50.0, 500.0, 5000.0];
+        let hist = log_histogram(&data, 1);
+        let total: usize = hist.iter().map(|&(_, _, c)| c).sum();
+        assert_eq!(total, data.len());
+        for &(low, high, _) in &hist {
+            assert!(low < high, "bucket [{low}, {high}) is not increasing");
+        }
+    }
+
+    #[test]
+    fn log_histogram_separates_non_positive_samples() {
+        let data = vec![-1.0, 0.0, 10.0, 100.0];
+        let hist = log_histogram(&data, 2);
+        assert_eq!(hist[0], (f64::NEG_INFINITY, 0.0, 2));
+        let positive_total: usize = hist[1..].iter().map(|&(_, _, c)| c).sum();
+        assert_eq!(positive_total, 2);
+    }
+
+    #[test]
+    fn log_histogram_empty_on_no_positive_samples() {
+        assert!(log_histogram(&[], 4).is_empty());
+        assert!(log_histogram(&[-1.0, -2.0], 0).len() == 1);
+    }
+
+    #[test]
+    fn histogram_with_edges_counts_underflow_buckets_and_overflow() {
+        let data = vec![-5.0, 50.0, 150.0, 250.0, 999.0];
+        let counts = histogram_with_edges(&data, &[0.0, 100.0, 200.0]).unwrap();
+        assert_eq!(counts, vec![1, 1, 1, 2]);
+    }
+
+    #[test]
+    fn histogram_with_edges_rejects_non_increasing_edges() {
+        assert_eq!(
+            histogram_with_edges(&[1.0], &[0.0, 0.0, 10.0]),
+            Err(InvalidEdges)
+        );
+        assert_eq!(
+            histogram_with_edges(&[1.0], &[10.0, 5.0]),
+            Err(InvalidEdges)
+        );
+    }
+
+    #[test]
+    fn detect_spikes_flags_a_jump_after_warmup() {
+        let mut series = Vec::new();
+        for _ in 0..10 {
+            series.push(statistics_from_sorted(&[100.0]));
+        }
+        series.push(statistics_from_sorted(&[10_000.0]));
+        let flagged = detect_spikes(&series, |s| s.p50, 3.0);
+        assert_eq!(flagged, vec![10]);
+    }
+
+    #[test]
+    fn detect_spikes_never_flags_the_warmup_window() {
+        let series: Vec<Statistics> = (0..SPIKE_WARMUP)
+            .map(|i| statistics_from_sorted(&[i as f64 * 1000.0]))
+            .collect();
+        assert!(detect_spikes(&series, |s| s.p50, 0.001).is_empty());
+    }
+
+    #[test]
+    fn detect_spikes_is_empty_for_a_flat_series() {
+        let series: Vec<Statistics> = (0..20).map(|_| statistics_from_sorted(&[42.0])).collect();
+        assert!(detect_spikes(&series, |s| s.p50, 3.0).is_empty());
+    }
+
+    #[test]
+    fn percentile_stability_is_zero_for_identical_runs() {
+        let runs = vec![statistics_from_sorted(&[1.0, 2.0, 3.0, 4.0, 5.0]); 5];
+        let stability = percentile_stability(&runs);
+        assert_eq!(stability.get("p50"), Some(&0.0));
+        assert_eq!(stability.get("p99"), Some(&0.0));
+    }
+
+    #[test]
+    fn percentile_stability_is_higher_for_noisier_runs() {
+        let stable: Vec<Statistics> = (0..10)
+            .map(|_| statistics_from_sorted(&[1.0, 2.0, 3.0, 4.0, 5.0]))
+            .collect();
+        let noisy: Vec<Statistics> = (0..10)
+            .map(|i| statistics_from_sorted(&[1.0, 2.0, 3.0, 4.0, 5.0 + i as f64 * 10.0]))
+            .collect();
+        let stable_cv = percentile_stability(&stable)["p99"];
+        let noisy_cv = percentile_stability(&noisy)["p99"];
+        assert!(noisy_cv > stable_cv, "noisy {noisy_cv} should exceed stable {stable_cv}");
+    }
+
+    #[test]
+    fn percentile_stability_ignores_nan_values_from_estimator_backed_runs() {
+        let mut runs = vec![statistics_from_sorted(&[1.0, 2.0, 3.0]); 3];
+        runs.push(Statistics { p50: f64::NAN, ..statistics_from_sorted(&[1.0, 2.0, 3.0]) });
+        let stability = percentile_stability(&runs);
+        assert_eq!(stability.get("p50"), Some(&0.0));
+    }
+
+    #[test]
+    fn percentile_stability_is_nan_with_fewer_than_two_surviving_runs() {
+        let runs = vec![Statistics { p50: f64::NAN, ..statistics_from_sorted(&[1.0]) }];
+        assert!(percentile_stability(&runs)["p50"].is_nan());
+    }
+
+    #[test]
+    fn percentile_stability_is_empty_for_no_runs() {
+        assert!(percentile_stability(&[]).is_empty());
+    }
+
+    #[test]
+    fn statistics_from_durations_scales_by_unit() {
+        let data = vec![
+            std::time::Duration::from_millis(100),
+            std::time::Duration::from_millis(200),
+        ];
+        let millis = statistics_from_durations(&data, TimeUnit::Millis);
+        assert_eq!(millis.min, 100.0);
+        assert_eq!(millis.max, 200.0);
+
+        let secs = statistics_from_durations(&data, TimeUnit::Seconds);
+        assert_eq!(secs.min, 0.1);
+        assert_eq!(secs.max, 0.2);
+    }
+
+    #[test]
+    fn time_unit_scale_matches_expected_factors() {
+        let d = std::time::Duration::from_secs(1);
+        assert_eq!(TimeUnit::Seconds.scale(d), 1.0);
+        assert_eq!(TimeUnit::Millis.scale(d), 1_000.0);
+        assert_eq!(TimeUnit::Micros.scale(d), 1_000_000.0);
+        assert_eq!(TimeUnit::Nanos.scale(d), 1_000_000_000.0);
+    }
+
+    #[test]
+    fn statistics_from_vec_f32_matches_f64_path() {
+        let f32_data: Vec<f32> = (1..=1000).map(|i| i as f32).collect();
+        let f64_data: Vec<f64> = (1..=1000).map(|i| i as f64).collect();
+        let from_f32 = statistics_from_vec_f32(f32_data);
+        let from_f64 = statistics_from_vec(f64_data);
+        assert_eq!(from_f32.cnt, from_f64.cnt);
+        assert_eq!(from_f32.min, from_f64.min);
+        assert_eq!(from_f32.max, from_f64.max);
+        assert!((from_f32.p50 - from_f64.p50).abs() < 1e-6);
+        assert!((from_f32.avg - from_f64.avg).abs() < 1e-6);
+    }
+
+    #[test]
+    fn statistics_from_vec_f32_empty_is_all_nan() {
+        let stat = statistics_from_vec_f32(Vec::new());
+        assert_eq!(stat.cnt, 0);
+        assert!(stat.avg.is_nan());
+    }
+
+    #[test]
+    fn approx_eq_treats_nan_as_equal_to_nan() {
+        let empty = statistics_from_sorted(&[]);
+        assert!(empty.approx_eq(&statistics_from_sorted(&[]), 0.01));
+    }
+
+    #[test]
+    fn approx_eq_respects_relative_tolerance() {
+        let a = statistics_from_sorted(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+        let mut b = a.clone();
+        b.p50 *= 1.001;
+        assert!(a.approx_eq(&b, 0.01));
+        b.p50 *= 2.0;
+        assert!(!a.approx_eq(&b, 0.01));
+    }
+
+    #[test]
+    fn assert_stats_approx_eq_macro_passes_on_matching_stats() {
+        let a = statistics_from_sorted(&[10.0, 20.0, 30.0]);
+        let b = a.clone();
+        crate::assert_stats_approx_eq!(a, b, 1e-9);
+    }
+
+    #[test]
+    fn nan_fields_serialize_as_null_and_back() {
+        let empty = statistics_from_sorted(&[]);
+        let json = serde_json::to_string(&empty).unwrap();
+        assert!(json.contains("\"avg\":null"));
+        let back: Statistics = serde_json::from_str(&json).unwrap();
+        assert!(back.avg.is_nan());
+        assert!(back.max.is_nan());
+        assert_eq!(back.cnt, 0);
+    }
 }
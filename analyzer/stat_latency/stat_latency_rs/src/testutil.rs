@@ -0,0 +1,178 @@
+//! Test-only accuracy comparison between an exact
+//! [`BruteQuantileState`](crate::quantile_brute::BruteQuantileState) and an
+//! approximate backend, so tuning a knob like [`TDigestQuantileState`]'s
+//! compression against real data doesn't mean re-writing the same ad-hoc
+//! comparison every time.
+#![cfg(test)]
+
+use crate::quantile_brute::BruteQuantileState;
+use crate::quantile_tdigest::{TDigestConfig, TDigestQuantileState};
+use crate::stats::QuantileInterpolation;
+
+/// Absolute error between `approx`'s quantile and `exact`'s exact quantile,
+/// for each `q` in `qs`, in the same order.
+pub(crate) fn quantile_error(
+    exact: &BruteQuantileState,
+    approx: &TDigestQuantileState,
+    qs: &[f64],
+) -> Vec<f64> {
+    qs.iter()
+        .map(|&q| {
+            let e = exact.quantile(q, QuantileInterpolation::Linear);
+            let a = approx.quantile(q);
+            (e - a).abs()
+        })
+        .collect()
+}
+
+/// Feed the same `data` into a fresh brute state and a fresh t-digest built
+/// with `compression`, then report the maximum absolute error across `qs`.
+pub(crate) fn max_quantile_error(data: &[f64], compression: usize, qs: &[f64]) -> f64 {
+    let mut exact = BruteQuantileState::new();
+    exact.extend(data.iter().copied());
+
+    let mut approx = TDigestQuantileState::with_config(TDigestConfig {
+        compression,
+        ..TDigestConfig::default()
+    });
+    approx.extend(data.iter().copied());
+    approx.merge();
+
+    quantile_error(&exact, &approx, qs)
+        .into_iter()
+        .fold(0.0, f64::max)
+}
+
+/// Assert that a fresh t-digest's answer for each `q` in `qs`, looked back up
+/// through the exact brute state's [`rank`](BruteQuantileState::rank), lands
+/// within `max_rank_error` of `q` itself. This is a *rank*-space accuracy
+/// bound ("the t-digest's p99 estimate is actually close to the true p99
+/// rank"), distinct from the *value*-space error [`max_quantile_error`]
+/// reports, and the one usually meant by "accurate to within 1%". Pins
+/// accuracy so a future `tdigests` bump or compression change can't silently
+/// degrade it without failing a test.
+pub(crate) fn assert_quantile_accuracy(values: &[f64], qs: &[f64], max_rank_error: f64) {
+    let mut exact = BruteQuantileState::new();
+    exact.extend(values.iter().copied());
+
+    let mut approx = TDigestQuantileState::new(values.len());
+    approx.extend(values.iter().copied());
+    approx.merge();
+
+    for &q in qs {
+        let estimate = approx.quantile(q);
+        let resolved_rank = exact.rank(estimate);
+        let error = (resolved_rank - q).abs();
+        assert!(
+            error <= max_rank_error,
+            "q={q} resolved to rank {resolved_rank} via estimate {estimate}, error {error} exceeds {max_rank_error}"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A tiny xorshift64 PRNG, kept internal so this test-only module carries
+    /// no RNG dependency, mirroring the same pattern used for production
+    /// downsampling in `quantile_brute`/`quantile_reservoir`.
+    struct Rng {
+        state: u64,
+    }
+
+    impl Rng {
+        fn new(seed: u64) -> Self {
+            Self { state: if seed == 0 { 0x9E37_79B9_7F4A_7C15 } else { seed } }
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            let mut x = self.state;
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            self.state = x;
+            x
+        }
+
+        /// A uniform `f64` in `(0, 1)`, excluding `0.0` so it's safe to feed
+        /// straight into `ln()` for the log-normal generator below.
+        fn next_open_unit_f64(&mut self) -> f64 {
+            let u = (self.next_u64() >> 11) as f64 / ((1u64 << 53) as f64);
+            u.max(f64::MIN_POSITIVE)
+        }
+    }
+
+    fn uniform_data(n: usize) -> Vec<f64> {
+        (1..=n).map(|i| i as f64).collect()
+    }
+
+    /// Log-normal samples via Box-Muller on two independent uniforms, so the
+    /// distribution is heavily right-skewed unlike the plain uniform ramp.
+    fn log_normal_data(n: usize, seed: u64) -> Vec<f64> {
+        let mut rng = Rng::new(seed);
+        (0..n)
+            .map(|_| {
+                let u1 = rng.next_open_unit_f64();
+                let u2 = rng.next_open_unit_f64();
+                let z = (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos();
+                z.exp()
+            })
+            .collect()
+    }
+
+    /// Two well-separated uniform clusters, so a single percentile grid can't
+    /// describe the shape (the case `bimodality_coefficient` is meant to
+    /// flag elsewhere).
+    fn bimodal_data(n: usize) -> Vec<f64> {
+        (0..n)
+            .map(|i| if i % 2 == 0 { i as f64 } else { 1_000_000.0 + i as f64 })
+            .collect()
+    }
+
+    #[test]
+    fn tdigest_rank_accuracy_holds_on_uniform_data() {
+        let data = uniform_data(100_000);
+        let qs = [0.5, 0.9, 0.99, 0.999];
+        assert_quantile_accuracy(&data, &qs, 0.01);
+    }
+
+    #[test]
+    fn tdigest_rank_accuracy_holds_on_log_normal_data() {
+        let data = log_normal_data(100_000, 1);
+        let qs = [0.5, 0.9, 0.99];
+        assert_quantile_accuracy(&data, &qs, 0.01);
+    }
+
+    #[test]
+    fn tdigest_rank_accuracy_holds_on_bimodal_data() {
+        let data = bimodal_data(100_000);
+        let qs = [0.25, 0.5, 0.75, 0.99];
+        assert_quantile_accuracy(&data, &qs, 0.01);
+    }
+
+    #[test]
+    fn tdigest_stays_within_tolerance_on_uniform_data() {
+        let data: Vec<f64> = (1..=100_000).map(|i| i as f64).collect();
+        let qs = [0.5, 0.9, 0.99, 0.999];
+        let err = max_quantile_error(&data, 200, &qs);
+        assert!(err < 500.0, "max error was {err}");
+    }
+
+    #[test]
+    fn tdigest_stays_within_tolerance_on_skewed_data() {
+        // A squared ramp is heavily right-skewed, unlike the uniform case.
+        let data: Vec<f64> = (1..=50_000).map(|i| (i as f64).powi(2)).collect();
+        let qs = [0.5, 0.9, 0.99];
+
+        let mut exact = BruteQuantileState::new();
+        exact.extend(data.iter().copied());
+        let exact_p99 = exact.quantile(0.99, QuantileInterpolation::Linear);
+
+        let err = max_quantile_error(&data, 200, &qs);
+        assert!(
+            err < exact_p99 * 0.1,
+            "max error was {err}, exact p99 {exact_p99}"
+        );
+    }
+}
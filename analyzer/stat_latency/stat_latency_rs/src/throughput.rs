@@ -0,0 +1,120 @@
+//! Request-rate helpers to pair with latency [`Statistics`](crate::stats::Statistics),
+//! so a summary reports throughput alongside percentiles instead of leaving
+//! load reconstruction up to every caller.
+
+use std::fmt;
+
+/// Overall throughput over a fixed window: `count` requests observed across
+/// `duration_secs`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Throughput {
+    pub count: usize,
+    pub duration_secs: f64,
+}
+
+impl Throughput {
+    pub fn from_count_and_duration(count: usize, duration_secs: f64) -> Self {
+        Self {
+            count,
+            duration_secs,
+        }
+    }
+
+    /// `count / duration_secs`, `NaN` if `duration_secs` isn't positive.
+    pub fn requests_per_second(&self) -> f64 {
+        if self.duration_secs <= 0.0 {
+            f64::NAN
+        } else {
+            self.count as f64 / self.duration_secs
+        }
+    }
+}
+
+/// Returned by [`rate_series`] when `bucket_secs` isn't positive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidBucketSecs;
+
+impl fmt::Display for InvalidBucketSecs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "bucket_secs must be positive")
+    }
+}
+
+impl std::error::Error for InvalidBucketSecs {}
+
+/// Bucket `timestamps_secs` (need not be sorted) into fixed `bucket_secs`-wide
+/// windows starting at the earliest timestamp, returning `(bucket_start,
+/// count)` pairs in ascending order — this is what lets a caller overlay
+/// request rate against a latency time series to correlate spikes with load.
+/// Buckets between the first and last sample with no samples are still
+/// included (count `0`) so the series has no gaps. Empty input returns an
+/// empty `Vec`. Errors with [`InvalidBucketSecs`] if `bucket_secs` isn't
+/// positive.
+pub fn rate_series(
+    timestamps_secs: &[f64],
+    bucket_secs: f64,
+) -> Result<Vec<(f64, usize)>, InvalidBucketSecs> {
+    if bucket_secs <= 0.0 {
+        return Err(InvalidBucketSecs);
+    }
+    if timestamps_secs.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let start = timestamps_secs.iter().cloned().fold(f64::INFINITY, f64::min);
+    let end = timestamps_secs
+        .iter()
+        .cloned()
+        .fold(f64::NEG_INFINITY, f64::max);
+    let bucket_count = (((end - start) / bucket_secs).floor() as usize) + 1;
+
+    let mut counts = vec![0usize; bucket_count];
+    for &t in timestamps_secs {
+        let idx = (((t - start) / bucket_secs) as usize).min(bucket_count - 1);
+        counts[idx] += 1;
+    }
+
+    Ok(counts
+        .into_iter()
+        .enumerate()
+        .map(|(i, count)| (start + i as f64 * bucket_secs, count))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_per_second_divides_count_by_duration() {
+        let t = Throughput::from_count_and_duration(1_000, 10.0);
+        assert_eq!(t.requests_per_second(), 100.0);
+    }
+
+    #[test]
+    fn requests_per_second_is_nan_for_zero_duration() {
+        let t = Throughput::from_count_and_duration(1_000, 0.0);
+        assert!(t.requests_per_second().is_nan());
+    }
+
+    #[test]
+    fn rate_series_buckets_timestamps_without_gaps() {
+        let timestamps = vec![0.5, 1.2, 1.9, 5.5];
+        let series = rate_series(&timestamps, 1.0).unwrap();
+        assert_eq!(
+            series,
+            vec![(0.5, 1), (1.5, 2), (2.5, 0), (3.5, 0), (4.5, 0), (5.5, 1)]
+        );
+    }
+
+    #[test]
+    fn rate_series_is_empty_for_empty_input() {
+        assert_eq!(rate_series(&[], 1.0).unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn rate_series_rejects_non_positive_bucket_secs() {
+        assert_eq!(rate_series(&[1.0], 0.0), Err(InvalidBucketSecs));
+        assert_eq!(rate_series(&[1.0], -1.0), Err(InvalidBucketSecs));
+    }
+}